@@ -1,4 +1,4 @@
-use std::fs;
+use std::{env, fs};
 
 struct Output {
     current_position: i32,
@@ -18,6 +18,32 @@ impl Default for Output {
 
 const MAX_POSITION: i32 = 100;
 
+struct Statistics {
+    current_position: i32,
+    landings: Vec<u64>,
+    crossings: Vec<u64>,
+    current_run_without_0: u64,
+    longest_run_without_0: u64,
+    cumulative_offset: i64,
+    min_cumulative_offset: i64,
+    max_cumulative_offset: i64,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Statistics {
+            current_position: 50,
+            landings: vec![0; MAX_POSITION as usize],
+            crossings: vec![0; MAX_POSITION as usize],
+            current_run_without_0: 0,
+            longest_run_without_0: 0,
+            cumulative_offset: 0,
+            min_cumulative_offset: 0,
+            max_cumulative_offset: 0,
+        }
+    }
+}
+
 fn get_number_from_chars(chars: &[char]) -> i32 {
     chars
         .iter()
@@ -40,16 +66,43 @@ fn get_times_passed_0(old_position: i32, movement: i32) -> i32 {
     }
 }
 
-fn fold_line(acc: Output, line: &str) -> Output {
+// Number of clicks during a movement that land on `target`, including the final one
+fn get_times_swept_over(old_position: i32, movement: i32, target: i32) -> i32 {
+    let distance = if movement > 0 {
+        (target - old_position).rem_euclid(MAX_POSITION)
+    } else {
+        (old_position - target).rem_euclid(MAX_POSITION)
+    };
+    // Starting on the target means a full turn is needed to reach it again
+    let distance = if distance == 0 {
+        MAX_POSITION
+    } else {
+        distance
+    };
+    let clicks = movement.abs();
+    let partial_turn = if distance <= clicks % MAX_POSITION {
+        1
+    } else {
+        0
+    };
+    clicks / MAX_POSITION + partial_turn
+}
+
+fn parse_movement(line: &str) -> Option<i32> {
     let chars: Vec<char> = line.chars().collect();
     match &chars[..] {
-        [direction, num @ ..] => {
-            let movement = match *direction {
-                'L' => -get_number_from_chars(num),
-                'R' => get_number_from_chars(num),
-                _ => panic!("Unknown direction: {}", direction),
-            };
+        [direction, num @ ..] => Some(match *direction {
+            'L' => -get_number_from_chars(num),
+            'R' => get_number_from_chars(num),
+            _ => panic!("Unknown direction: {}", direction),
+        }),
+        _ => None,
+    }
+}
 
+fn fold_line(acc: Output, line: &str) -> Output {
+    match parse_movement(line) {
+        Some(movement) => {
             let current_position = (acc.current_position + movement).rem_euclid(MAX_POSITION);
 
             let count_of_times_reached_0 =
@@ -64,10 +117,37 @@ fn fold_line(acc: Output, line: &str) -> Output {
                 count_of_times_passed_0,
             }
         }
-        _ => acc,
+        None => acc,
     }
 }
 
+fn fold_line_statistics(mut acc: Statistics, line: &str) -> Statistics {
+    let Some(movement) = parse_movement(line) else {
+        return acc;
+    };
+
+    let old_position = acc.current_position;
+    for target in 0..MAX_POSITION {
+        acc.crossings[target as usize] +=
+            get_times_swept_over(old_position, movement, target) as u64;
+    }
+
+    acc.current_position = (old_position + movement).rem_euclid(MAX_POSITION);
+    acc.landings[acc.current_position as usize] += 1;
+
+    if get_times_swept_over(old_position, movement, 0) > 0 {
+        acc.current_run_without_0 = 0;
+    } else {
+        acc.current_run_without_0 += 1;
+        acc.longest_run_without_0 = acc.longest_run_without_0.max(acc.current_run_without_0);
+    }
+
+    acc.cumulative_offset += movement as i64;
+    acc.min_cumulative_offset = acc.min_cumulative_offset.min(acc.cumulative_offset);
+    acc.max_cumulative_offset = acc.max_cumulative_offset.max(acc.cumulative_offset);
+    acc
+}
+
 fn main() {
     match fs::read_to_string("./day-1/assets/input.txt") {
         Ok(contents) => {
@@ -78,6 +158,33 @@ fn main() {
                 output.count_of_times_reached_0,
                 output.count_of_times_passed_0
             );
+
+            // Pass `stats` to also print where the dial landed and what it swept over, e.g.
+            // `cargo run -p day-1 -- stats`
+            if env::args().nth(1).as_deref() == Some("stats") {
+                let statistics = contents
+                    .split("\n")
+                    .fold(Default::default(), fold_line_statistics);
+                let most_landed = (0..MAX_POSITION as usize)
+                    .max_by_key(|&position| statistics.landings[position])
+                    .unwrap();
+                let most_crossed = (0..MAX_POSITION as usize)
+                    .max_by_key(|&position| statistics.crossings[position])
+                    .unwrap();
+                println!(
+                    "Most landed: {} ({} times), Most crossed: {} ({} times)",
+                    most_landed,
+                    statistics.landings[most_landed],
+                    most_crossed,
+                    statistics.crossings[most_crossed]
+                );
+                println!(
+                    "Longest run without 0: {}, Cumulative offset range: {}..={}",
+                    statistics.longest_run_without_0,
+                    statistics.min_cumulative_offset,
+                    statistics.max_cumulative_offset
+                );
+            }
         }
         Err(e) => eprintln!("Error reading file: {}", e),
     }
@@ -85,7 +192,9 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::get_times_passed_0;
+    use crate::{fold_line_statistics, get_times_passed_0, get_times_swept_over, Statistics};
+
+    const EXAMPLE: &str = "L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82";
 
     #[test]
     fn computes_forward_wrap() {
@@ -156,4 +265,41 @@ mod tests {
     fn computes_no_wrap_from_zero_forwards() {
         assert_eq!(get_times_passed_0(0, 50), 0);
     }
+
+    #[test]
+    fn swept_over_0_matches_times_passed_0() {
+        for old_position in 0..100 {
+            for movement in [-250, -110, -100, -50, -1, 1, 50, 90, 100, 190, 250] {
+                assert_eq!(
+                    get_times_swept_over(old_position, movement, 0),
+                    get_times_passed_0(old_position, movement)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn computes_swept_over_other_position() {
+        assert_eq!(get_times_swept_over(10, 250, 20), 3);
+        assert_eq!(get_times_swept_over(10, 250, 70), 2);
+        assert_eq!(get_times_swept_over(10, -15, 99), 1);
+        assert_eq!(get_times_swept_over(10, 100, 10), 1);
+    }
+
+    #[test]
+    fn statistics_work() {
+        let statistics: Statistics = EXAMPLE
+            .split("\n")
+            .fold(Default::default(), fold_line_statistics);
+        assert_eq!(statistics.landings[0], 3);
+        assert_eq!(statistics.crossings[0], 6);
+        assert_eq!(statistics.landings.iter().sum::<u64>(), 10);
+        assert_eq!(
+            statistics.crossings.iter().sum::<u64>(),
+            [68, 30, 48, 5, 60, 55, 1, 99, 14, 82].iter().sum::<u64>()
+        );
+        assert_eq!(statistics.longest_run_without_0, 1);
+        assert_eq!(statistics.min_cumulative_offset, -218);
+        assert_eq!(statistics.max_cumulative_offset, 5);
+    }
 }
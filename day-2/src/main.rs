@@ -1,53 +1,293 @@
-use std::fs;
 use std::ops::RangeInclusive;
+use std::{env, fs, thread};
 
+const DEFAULT_RADIX: u32 = 10;
+
+#[cfg(test)]
 fn convert_line_into_range(line: &str) -> RangeInclusive<u64> {
+    convert_line_into_range_with_radix(line, DEFAULT_RADIX)
+}
+
+fn convert_line_into_range_with_radix(line: &str, radix: u32) -> RangeInclusive<u64> {
     let numbers: Vec<_> = line.split("-").collect();
-    let start: u64 = numbers[0].parse().expect("Failed to parse start number");
-    let end: u64 = numbers[1].parse().expect("Failed to parse end number");
+    let start = u64::from_str_radix(numbers[0], radix).expect("Failed to parse start number");
+    let end = u64::from_str_radix(numbers[1], radix).expect("Failed to parse end number");
     start..=end
 }
 
+fn format_in_radix(n: u128, radix: u32) -> String {
+    let mut digits = vec![];
+    let mut remaining = n;
+    loop {
+        digits.push(char::from_digit((remaining % radix as u128) as u32, radix).unwrap());
+        remaining /= radix as u128;
+        if remaining == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Repetitions {
+    Exactly(u32),
+    AtLeast(u32),
+}
+
+impl Repetitions {
+    fn for_part(part: u8) -> Self {
+        match part {
+            1 => Repetitions::Exactly(2),
+            2 => Repetitions::AtLeast(2),
+            _ => panic!("Unknown part: {}", part),
+        }
+    }
+
+    fn allows(self, repetitions: u32) -> bool {
+        match self {
+            Repetitions::Exactly(k) => repetitions == k,
+            Repetitions::AtLeast(k) => repetitions >= k,
+        }
+    }
+}
+
+#[cfg(test)]
+fn is_repeated_exactly(s: &str, k: usize) -> bool {
+    if k == 0 || s.is_empty() || !s.len().is_multiple_of(k) {
+        return false;
+    }
+    let bytes = s.as_bytes();
+    let block = &bytes[..s.len() / k];
+    bytes.chunks(block.len()).all(|chunk| chunk == block)
+}
+
+#[cfg(test)]
+fn is_repeated_at_least(s: &str, k: usize) -> bool {
+    (k.max(1)..=s.len()).any(|repetitions| is_repeated_exactly(s, repetitions))
+}
+
+#[cfg(test)]
+fn is_invalid_id_for(s: &str, rule: Repetitions) -> bool {
+    match rule {
+        Repetitions::Exactly(k) => is_repeated_exactly(s, k as usize),
+        Repetitions::AtLeast(k) => is_repeated_at_least(s, k as usize),
+    }
+}
+
+#[cfg(test)]
 fn is_invalid_id(s: &str) -> bool {
-    (1..=s.len() / 2).any(|size| {
-        let char_arr: Vec<char> = s.chars().collect();
-        let char_chunks: Vec<_> = char_arr.chunks(size).collect();
-        let str_chunks: Vec<String> = char_chunks
-            .iter()
-            .map(|chunk| chunk.iter().collect())
+    is_invalid_id_for(s, Repetitions::AtLeast(2))
+}
+
+fn count_digits(n: u64, radix: u32) -> u32 {
+    n.checked_ilog(radix as u64).unwrap_or(0) + 1
+}
+
+// Builds e.g. 1001001 for a block length of 3 repeated 3 times, so that block * multiplier repeats the block
+fn get_repetition_multiplier(block_length: u32, repetitions: u32, radix: u32) -> u128 {
+    let block_size = (radix as u128).pow(block_length);
+    (0..repetitions).fold(0, |acc, _| acc * block_size + 1)
+}
+
+// The smallest and largest blocks that, repeated to make an id with the given digit length, land in the range
+fn get_block_range(
+    range: &RangeInclusive<u64>,
+    block_length: u32,
+    multiplier: u128,
+    radix: u32,
+) -> RangeInclusive<u128> {
+    let radix_wide = radix as u128;
+    let min_block = radix_wide
+        .pow(block_length - 1)
+        .max((*range.start() as u128).div_ceil(multiplier));
+    let max_block = (radix_wide.pow(block_length) - 1).min(*range.end() as u128 / multiplier);
+    min_block..=max_block
+}
+
+// Generates every id in the range made of a repeated digit block allowed by the rule, without visiting any other ids
+#[cfg(test)]
+fn generate_repeated_pattern_ids(
+    range: RangeInclusive<u64>,
+    rule: Repetitions,
+    radix: u32,
+) -> (Vec<u64>, u128) {
+    assert!((2..=36).contains(&radix), "Radix must be between 2 and 36");
+    let mut ids = vec![];
+    for digit_length in count_digits(*range.start(), radix)..=count_digits(*range.end(), radix) {
+        let repetition_counts = (1..=digit_length)
+            .filter(|repetitions| digit_length % repetitions == 0 && rule.allows(*repetitions));
+        for repetitions in repetition_counts {
+            let block_length = digit_length / repetitions;
+            let multiplier = get_repetition_multiplier(block_length, repetitions, radix);
+            for block in get_block_range(&range, block_length, multiplier, radix) {
+                let id = (block * multiplier) as u64;
+                debug_assert!(is_invalid_id_for(&format_in_radix(id as u128, radix), rule));
+                ids.push(id);
+            }
+        }
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    let sum = ids.iter().map(|&id| id as u128).sum();
+    (ids, sum)
+}
+
+// Sums the ids with the given digit length in the range that repeat every block_length digits, which is
+// the sum of the blocks times the multiplier
+fn sum_ids_with_period(
+    range: &RangeInclusive<u64>,
+    digit_length: u32,
+    block_length: u32,
+    radix: u32,
+) -> u128 {
+    let multiplier = get_repetition_multiplier(block_length, digit_length / block_length, radix);
+    let blocks = get_block_range(range, block_length, multiplier, radix);
+    if blocks.is_empty() {
+        return 0;
+    }
+    let (min_block, max_block) = (*blocks.start(), *blocks.end());
+    // Halves whichever factor is even so that the product stays below the sum of the ids
+    let count = max_block - min_block + 1;
+    let block_sum = if count % 2 == 0 {
+        count / 2 * (min_block + max_block)
+    } else {
+        count * ((min_block + max_block) / 2)
+    };
+    block_sum * multiplier
+}
+
+// Sums the same ids as generate_repeated_pattern_ids without listing them. An id repeating every d and
+// every e digits also repeats every gcd(d, e) digits, so each id is summed once under the shortest block
+// length it repeats with, by taking away the ids already summed under the block lengths dividing it
+fn sum_repeated_pattern_ids(range: RangeInclusive<u64>, rule: Repetitions, radix: u32) -> u128 {
+    assert!((2..=36).contains(&radix), "Radix must be between 2 and 36");
+    let mut sum = 0;
+    for digit_length in count_digits(*range.start(), radix)..=count_digits(*range.end(), radix) {
+        let block_lengths: Vec<u32> = (1..=digit_length)
+            .filter(|block_length| digit_length % block_length == 0)
             .collect();
-        str_chunks.iter().all(|chunk| *chunk == str_chunks[0])
-    })
+        let mut sums_by_shortest_block: Vec<u128> = vec![];
+        for (i, &block_length) in block_lengths.iter().enumerate() {
+            let shorter: u128 = block_lengths[..i]
+                .iter()
+                .zip(&sums_by_shortest_block)
+                .filter(|(shorter_length, _)| block_length % **shorter_length == 0)
+                .map(|(_, shorter_sum)| shorter_sum)
+                .sum();
+            sums_by_shortest_block
+                .push(sum_ids_with_period(&range, digit_length, block_length, radix) - shorter);
+        }
+        // An id is allowed when some allowed block length is a multiple of its shortest one
+        for (&shortest, shortest_sum) in block_lengths.iter().zip(&sums_by_shortest_block) {
+            let is_allowed = block_lengths.iter().any(|&block_length| {
+                block_length % shortest == 0 && rule.allows(digit_length / block_length)
+            });
+            if is_allowed {
+                sum += shortest_sum;
+            }
+        }
+    }
+    sum
 }
 
-fn find_invalid_ids_in_range(range: RangeInclusive<u64>) -> Vec<u64> {
-    range.fold(vec![], |acc, id| {
-        if is_invalid_id(&*id.to_string()) {
-            [acc, vec![id]].concat()
-        } else {
+fn sum_total_invalid_ids_in_input(input: String, part: u8) -> u128 {
+    sum_total_invalid_ids_in_input_with_radix(input, part, DEFAULT_RADIX)
+}
+
+fn parse_ranges(input: &str, radix: u32) -> Vec<RangeInclusive<u64>> {
+    input
+        .split(",")
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| convert_line_into_range_with_radix(line, radix))
+        .collect()
+}
+
+// Sorts the ranges and merges any that overlap or touch, so no id is counted twice
+fn merge_ranges(mut ranges: Vec<RangeInclusive<u64>>) -> Vec<RangeInclusive<u64>> {
+    ranges.sort_by_key(|range| *range.start());
+    ranges
+        .into_iter()
+        .fold(vec![], |mut acc: Vec<RangeInclusive<u64>>, range| {
+            match acc.last_mut() {
+                Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                    *last = *last.start()..=*last.end().max(range.end());
+                }
+                _ => acc.push(range),
+            }
             acc
-        }
-    })
+        })
+}
+
+// The ranges are merged, so the total is a sum of distinct u64 ids and cannot overflow a u128
+fn sum_invalid_ids_in_ranges(
+    ranges: &[RangeInclusive<u64>],
+    rule: Repetitions,
+    radix: u32,
+) -> u128 {
+    ranges
+        .iter()
+        .map(|range| sum_repeated_pattern_ids(range.clone(), rule, radix))
+        .sum()
 }
 
-fn sum_total_invalid_ids_in_input(input: String) -> u64 {
-    input.split(",").fold(0, |acc, line| {
-        let invalid_ids = find_invalid_ids_in_range(convert_line_into_range(line));
-        acc + invalid_ids.iter().sum::<u64>()
+// Splits the ranges into one chunk per thread, summing the chunk totals in order once they are joined
+fn sum_invalid_ids_in_ranges_in_parallel(
+    ranges: &[RangeInclusive<u64>],
+    rule: Repetitions,
+    radix: u32,
+) -> u128 {
+    let thread_count = thread::available_parallelism().map_or(1, |count| count.get());
+    let chunk_size = ranges.len().div_ceil(thread_count).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = ranges
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || sum_invalid_ids_in_ranges(chunk, rule, radix)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Range worker panicked"))
+            .sum()
     })
 }
 
+fn sum_total_invalid_ids_in_input_with_radix(input: String, part: u8, radix: u32) -> u128 {
+    let ranges = merge_ranges(parse_ranges(&input, radix));
+    sum_invalid_ids_in_ranges_in_parallel(&ranges, Repetitions::for_part(part), radix)
+}
+
 fn main() {
+    // Optionally pass a radix to read the ranges in, e.g. `cargo run -p day-2 -- 16`
+    let radix = env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("Failed to parse radix"));
     match fs::read_to_string("./day-2/assets/input.txt") {
-        Ok(contents) => {
-            println!("{}", sum_total_invalid_ids_in_input(contents));
-        }
+        Ok(contents) => match radix {
+            Some(radix) => {
+                for part in [1, 2] {
+                    let total =
+                        sum_total_invalid_ids_in_input_with_radix(contents.clone(), part, radix);
+                    println!("Part {}: {}", part, format_in_radix(total, radix));
+                }
+            }
+            None => {
+                println!(
+                    "Part 1: {}",
+                    sum_total_invalid_ids_in_input(contents.clone(), 1)
+                );
+                println!("Part 2: {}", sum_total_invalid_ids_in_input(contents, 2));
+            }
+        },
         Err(e) => eprintln!("Error reading file: {}", e),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::Repetitions;
+
+    const EXAMPLE: &str = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
+
     #[test]
     fn convert_line_works() {
         let line = "10-20";
@@ -67,19 +307,243 @@ mod tests {
     #[test]
     fn count_invalid_ids_in_range_works() {
         let range = 11..=22;
-        let invalid_ids = super::find_invalid_ids_in_range(range);
+        let invalid_ids =
+            super::generate_repeated_pattern_ids(range, Repetitions::AtLeast(2), 10).0;
         assert_eq!(invalid_ids, vec![11, 22]);
         let range = 1188511880..=1188511890;
-        let invalid_ids = super::find_invalid_ids_in_range(range);
+        let invalid_ids =
+            super::generate_repeated_pattern_ids(range, Repetitions::AtLeast(2), 10).0;
         assert_eq!(invalid_ids, vec![1188511885]);
     }
 
     #[test]
     fn sum_total_invalid_ids_in_input_works() {
-        let input = String::from(
-            "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124",
-        );
-        let total = super::sum_total_invalid_ids_in_input(input);
+        let total = super::sum_total_invalid_ids_in_input(EXAMPLE.to_string(), 2);
         assert_eq!(total, 4174379265);
     }
+
+    #[test]
+    fn sum_total_invalid_ids_in_input_works_for_part_1() {
+        let total = super::sum_total_invalid_ids_in_input(EXAMPLE.to_string(), 1);
+        assert_eq!(total, 1227775554);
+    }
+
+    fn sum_example_with_rule(rule: Repetitions) -> u128 {
+        EXAMPLE
+            .split(",")
+            .map(|line| {
+                super::sum_repeated_pattern_ids(super::convert_line_into_range(line), rule, 10)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn sum_example_repeated_exactly_k_times() {
+        assert_eq!(sum_example_with_rule(Repetitions::Exactly(2)), 1227775554);
+        // 222222 is made of both two and three blocks, so it is counted under each rule
+        assert_eq!(
+            sum_example_with_rule(Repetitions::Exactly(3)),
+            111 + 999 + 565656 + 824824824 + 222222
+        );
+        assert_eq!(sum_example_with_rule(Repetitions::Exactly(5)), 2121212121);
+    }
+
+    #[test]
+    fn sum_example_repeated_at_least_k_times() {
+        assert_eq!(sum_example_with_rule(Repetitions::AtLeast(2)), 4174379265);
+        assert_eq!(
+            sum_example_with_rule(Repetitions::AtLeast(5)),
+            2121212121 + 222222
+        );
+    }
+
+    #[test]
+    fn is_repeated_exactly_works() {
+        assert!(super::is_repeated_exactly("abcabc", 2));
+        assert!(!super::is_repeated_exactly("abcabc", 3));
+        assert!(super::is_repeated_exactly("111", 3));
+        assert!(super::is_repeated_exactly("12121212", 4));
+        assert!(super::is_repeated_exactly("12121212", 2));
+        assert!(!super::is_repeated_exactly("121212", 2));
+    }
+
+    #[test]
+    fn is_repeated_at_least_works() {
+        assert!(super::is_repeated_at_least("121212", 2));
+        assert!(super::is_repeated_at_least("111", 3));
+        assert!(!super::is_repeated_at_least("abcabc", 3));
+        assert!(!super::is_repeated_at_least("abcab", 2));
+    }
+
+    #[test]
+    fn repetitions_for_part_works() {
+        assert_eq!(Repetitions::for_part(1), Repetitions::Exactly(2));
+        assert_eq!(Repetitions::for_part(2), Repetitions::AtLeast(2));
+    }
+
+    #[test]
+    fn generate_repeated_pattern_ids_works() {
+        assert_eq!(
+            super::generate_repeated_pattern_ids(95..=115, Repetitions::AtLeast(2), 10),
+            (vec![99, 111], 210)
+        );
+        assert_eq!(
+            super::generate_repeated_pattern_ids(998..=1012, Repetitions::AtLeast(2), 10),
+            (vec![999, 1010], 2009)
+        );
+        assert_eq!(
+            super::generate_repeated_pattern_ids(1..=9, Repetitions::AtLeast(2), 10),
+            (vec![], 0)
+        );
+    }
+
+    #[test]
+    fn generate_repeated_pattern_ids_deduplicates() {
+        // 222222 is 2 repeated six times, 22 three times and 222 twice
+        let (ids, sum) =
+            super::generate_repeated_pattern_ids(222220..=222224, Repetitions::AtLeast(2), 10);
+        assert_eq!(ids, vec![222222]);
+        assert_eq!(sum, 222222);
+    }
+
+    #[test]
+    fn generate_repeated_pattern_ids_matches_scan() {
+        let range = 1..=20000u64;
+        let scanned: Vec<u64> = range
+            .clone()
+            .filter(|id| super::is_invalid_id(&id.to_string()))
+            .collect();
+        assert_eq!(
+            super::generate_repeated_pattern_ids(range, Repetitions::AtLeast(2), 10).0,
+            scanned
+        );
+    }
+
+    #[test]
+    fn generate_repeated_pattern_ids_handles_huge_ranges() {
+        let range = 10u64.pow(17)..=10u64.pow(17) + 10u64.pow(10);
+        let (ids, sum) =
+            super::generate_repeated_pattern_ids(range.clone(), Repetitions::AtLeast(2), 10);
+        assert_eq!(ids[0], 100000000100000000);
+        assert!(ids.iter().all(|id| range.contains(id)));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(sum, ids.iter().map(|&id| id as u128).sum::<u128>());
+        assert_eq!(
+            super::sum_repeated_pattern_ids(range, Repetitions::AtLeast(2), 10),
+            sum
+        );
+    }
+
+    #[test]
+    fn format_in_radix_works() {
+        assert_eq!(super::format_in_radix(0, 10), "0");
+        assert_eq!(super::format_in_radix(255, 16), "ff");
+        assert_eq!(super::format_in_radix(5, 2), "101");
+        assert_eq!(super::format_in_radix(35, 36), "z");
+    }
+
+    #[test]
+    fn convert_line_with_radix_round_trips() {
+        let range = super::convert_line_into_range_with_radix("1a-FF", 16);
+        assert_eq!(range, 26..=255);
+        assert_eq!(super::format_in_radix(*range.start() as u128, 16), "1a");
+        assert_eq!(super::format_in_radix(*range.end() as u128, 16), "ff");
+    }
+
+    #[test]
+    fn generate_repeated_pattern_ids_works_in_hex() {
+        let (ids, _) =
+            super::generate_repeated_pattern_ids(0x10..=0x1ff, Repetitions::AtLeast(2), 16);
+        assert_eq!(ids.len(), 15 + 1);
+        assert_eq!(ids[0], 0x11);
+        assert_eq!(ids[ids.len() - 1], 0x111);
+    }
+
+    #[test]
+    fn generate_repeated_pattern_ids_matches_scan_in_every_radix() {
+        for radix in 2..=36 {
+            for rule in [Repetitions::Exactly(2), Repetitions::AtLeast(2)] {
+                let range = 1..=3000u64;
+                let scanned: Vec<u64> = range
+                    .clone()
+                    .filter(|id| {
+                        super::is_invalid_id_for(&super::format_in_radix(*id as u128, radix), rule)
+                    })
+                    .collect();
+                assert_eq!(
+                    super::generate_repeated_pattern_ids(range, rule, radix).0,
+                    scanned
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sum_total_invalid_ids_in_input_works_in_base_36() {
+        // zz is 1295 and 1010 in base 36 is 46692
+        let total =
+            super::sum_total_invalid_ids_in_input_with_radix("zy-zz,zzz-1010".to_string(), 1, 36);
+        assert_eq!(total, 1295 + 46692);
+    }
+
+    #[test]
+    fn parse_ranges_tolerates_whitespace() {
+        let ranges = super::parse_ranges(" 11-22 ,\n95-115,\r\n998-1012\n", 10);
+        assert_eq!(ranges, vec![11..=22, 95..=115, 998..=1012]);
+    }
+
+    #[test]
+    fn merge_ranges_works() {
+        let ranges = vec![95..=115, 11..=22, 20..=30, 31..=40, 100..=101, 50..=60];
+        assert_eq!(
+            super::merge_ranges(ranges),
+            vec![11..=40, 50..=60, 95..=115]
+        );
+    }
+
+    #[test]
+    fn sum_total_invalid_ids_in_input_does_not_double_count_overlaps() {
+        let total = super::sum_total_invalid_ids_in_input("11-22,15-33,22-22".to_string(), 1);
+        assert_eq!(total, 11 + 22 + 33);
+    }
+
+    #[test]
+    fn parallel_sum_matches_sequential_sum() {
+        let ranges = super::merge_ranges(super::parse_ranges(EXAMPLE, 10));
+        for rule in [Repetitions::Exactly(2), Repetitions::AtLeast(2)] {
+            assert_eq!(
+                super::sum_invalid_ids_in_ranges_in_parallel(&ranges, rule, 10),
+                super::sum_invalid_ids_in_ranges(&ranges, rule, 10)
+            );
+        }
+    }
+
+    #[test]
+    fn sum_repeated_pattern_ids_matches_generated_sum() {
+        for radix in 2..=36 {
+            for rule in [
+                Repetitions::Exactly(2),
+                Repetitions::Exactly(3),
+                Repetitions::AtLeast(1),
+                Repetitions::AtLeast(2),
+                Repetitions::AtLeast(4),
+            ] {
+                for range in [1..=3000u64, 100..=100, 20000..=30000] {
+                    assert_eq!(
+                        super::sum_repeated_pattern_ids(range.clone(), rule, radix),
+                        super::generate_repeated_pattern_ids(range, rule, radix).1
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sum_repeated_pattern_ids_handles_the_whole_u64_range() {
+        // Every id is made of one block, so the sum is that of every u64 and would overflow one
+        let sum = super::sum_repeated_pattern_ids(0..=u64::MAX, Repetitions::AtLeast(1), 10);
+        assert_eq!(sum, u64::MAX as u128 * (u64::MAX as u128 + 1) / 2);
+        let sum = super::sum_repeated_pattern_ids(0..=u64::MAX, Repetitions::AtLeast(2), 10);
+        assert!(sum > u64::MAX as u128);
+    }
 }
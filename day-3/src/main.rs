@@ -1,9 +1,82 @@
 use std::fs;
 
+fn parse_bank(line: &str) -> Vec<u8> {
+    line.chars()
+        .map(|c| {
+            c.to_digit(10)
+                .unwrap_or_else(|| panic!("Unexpected battery: {}", c)) as u8
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Objective {
+    Maximise,
+    Minimise,
+}
+
+// Picks k items in order to form the largest (or smallest) sequence, using a monotonic stack: an item is
+// dropped whenever a better one follows it, as long as enough items remain to still pick k
+fn select_k_subsequence<T: Ord>(items: &[T], k: usize, objective: Objective) -> Vec<usize> {
+    assert!(
+        k <= items.len(),
+        "Cannot pick {} items from {}",
+        k,
+        items.len()
+    );
+    let is_improvement = |top: &T, next: &T| match objective {
+        Objective::Maximise => top < next,
+        Objective::Minimise => top > next,
+    };
+    let mut drops_remaining = items.len() - k;
+    let mut stack: Vec<usize> = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        while drops_remaining > 0
+            && stack
+                .last()
+                .is_some_and(|&top| is_improvement(&items[top], item))
+        {
+            stack.pop();
+            drops_remaining -= 1;
+        }
+        stack.push(i);
+    }
+    stack.truncate(k);
+    stack
+}
+
+// Returns None rather than wrapping when the selected digits do not fit in a u128
+fn get_joltage(bank: &[u8], indices: &[usize]) -> Option<u128> {
+    indices.iter().try_fold(0u128, |acc, &i| {
+        acc.checked_mul(10)?.checked_add(bank[i] as u128)
+    })
+}
+
+// Sums the best joltage of every bank, returning None if any bank or the total overflows a u128
+fn sum_joltage(input: &str, k: usize, objective: Objective) -> Option<u128> {
+    input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .try_fold(0u128, |acc, line| {
+            let bank = parse_bank(line);
+            let indices = select_k_subsequence(&bank, k, objective);
+            acc.checked_add(get_joltage(&bank, &indices)?)
+        })
+}
 
 fn main() {
     match fs::read_to_string("./day-3/assets/input.txt") {
         Ok(contents) => {
+            for (part, k) in [(1, 2), (2, 12)] {
+                match sum_joltage(&contents, k, Objective::Maximise) {
+                    Some(total) => println!("Part {}: {}", part, total),
+                    None => println!("Part {} overflows a u128", part),
+                }
+            }
+            match sum_joltage(&contents, 12, Objective::Minimise) {
+                Some(total) => println!("Minimum 12 battery joltage: {}", total),
+                None => println!("Minimum 12 battery joltage overflows a u128"),
+            }
         }
         Err(e) => eprintln!("Error reading file: {}", e),
     }
@@ -11,4 +84,121 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn select_batteries(bank: &[u8], k: usize) -> (Vec<usize>, u128) {
+        let indices = select_k_subsequence(bank, k, Objective::Maximise);
+        let value = get_joltage(bank, &indices).unwrap();
+        (indices, value)
+    }
+
+    const EXAMPLE: &str = "987654321111111
+811111111111119
+234234234234278
+818181911112111";
+
+    #[test]
+    fn parse_bank_works() {
+        assert_eq!(parse_bank("8109"), vec![8, 1, 0, 9]);
+    }
+
+    #[test]
+    fn select_two_batteries_works() {
+        assert_eq!(
+            select_batteries(&parse_bank("987654321111111"), 2),
+            (vec![0, 1], 98)
+        );
+        assert_eq!(
+            select_batteries(&parse_bank("811111111111119"), 2),
+            (vec![0, 14], 89)
+        );
+        assert_eq!(
+            select_batteries(&parse_bank("234234234234278"), 2),
+            (vec![13, 14], 78)
+        );
+        assert_eq!(
+            select_batteries(&parse_bank("818181911112111"), 2),
+            (vec![6, 11], 92)
+        );
+    }
+
+    #[test]
+    fn select_twelve_batteries_works() {
+        let (indices, value) = select_batteries(&parse_bank("234234234234278"), 12);
+        assert_eq!(value, 434234234278);
+        assert_eq!(indices, vec![2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+        assert_eq!(
+            select_batteries(&parse_bank("818181911112111"), 12).1,
+            888911112111
+        );
+    }
+
+    #[test]
+    fn select_all_batteries_keeps_bank() {
+        assert_eq!(
+            select_batteries(&parse_bank("123"), 3),
+            (vec![0, 1, 2], 123)
+        );
+    }
+
+    #[test]
+    fn select_k_subsequence_minimises() {
+        let bank = parse_bank("818181911112111");
+        let indices = select_k_subsequence(&bank, 4, Objective::Minimise);
+        assert_eq!(get_joltage(&bank, &indices), Some(1111));
+        let bank = parse_bank("4321");
+        assert_eq!(
+            select_k_subsequence(&bank, 2, Objective::Minimise),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn select_k_subsequence_works_on_any_ord() {
+        let items = ['b', 'a', 'c', 'a', 'b'];
+        assert_eq!(
+            select_k_subsequence(&items, 3, Objective::Maximise),
+            vec![2, 3, 4]
+        );
+        assert_eq!(select_k_subsequence(&items, 0, Objective::Maximise), vec![]);
+    }
+
+    #[test]
+    fn get_joltage_detects_overflow() {
+        let bank = vec![9; 40];
+        let indices: Vec<usize> = (0..39).collect();
+        assert_eq!(get_joltage(&bank, &indices[..38]), Some(10u128.pow(38) - 1));
+        assert_eq!(get_joltage(&bank, &indices), None);
+    }
+
+    #[test]
+    fn sum_joltage_works() {
+        assert_eq!(sum_joltage(EXAMPLE, 2, Objective::Maximise), Some(357));
+        assert_eq!(
+            sum_joltage(EXAMPLE, 12, Objective::Maximise),
+            Some(3121910778619)
+        );
+        assert_eq!(
+            sum_joltage(EXAMPLE, 2, Objective::Minimise),
+            Some(11 + 11 + 22 + 11)
+        );
+        assert_eq!(sum_joltage(&"9".repeat(50), 40, Objective::Maximise), None);
+    }
+
+    // Run with `cargo test --release -p day-3 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_million_digit_bank() {
+        let bank: Vec<u8> = (0..1_000_000u64)
+            .map(|i| (i.wrapping_mul(2654435761) % 10) as u8)
+            .collect();
+        for k in [12, 1_000, 500_000] {
+            for objective in [Objective::Maximise, Objective::Minimise] {
+                let start = std::time::Instant::now();
+                let indices = select_k_subsequence(&bank, k, objective);
+                println!("k={} {:?}: {:?}", k, objective, start.elapsed());
+                assert_eq!(indices.len(), k);
+            }
+        }
+    }
 }
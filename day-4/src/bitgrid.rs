@@ -0,0 +1,324 @@
+use crate::{Boundary, Rule};
+
+const WORD_BITS: usize = 64;
+
+// One bit per cell, with column c of a row stored in bit c % 64 of word c / 64
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn from_char_grid(grid: &[Vec<char>], occupied: char) -> Self {
+        let height = grid.len();
+        let width = grid.first().map_or(0, |line| line.len());
+        let words_per_row = width.div_ceil(WORD_BITS);
+        let mut words = vec![0; words_per_row * height];
+        for (row, line) in grid.iter().enumerate() {
+            for (col, cell) in line.iter().enumerate() {
+                if *cell == occupied {
+                    words[row * words_per_row + col / WORD_BITS] |= 1 << (col % WORD_BITS);
+                }
+            }
+        }
+        BitGrid {
+            width,
+            height,
+            words_per_row,
+            words,
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    // Clears the bits past the end of the row in the last word
+    fn mask_row(&self, words: &mut [u64]) {
+        let used_bits = self.width % WORD_BITS;
+        if used_bits != 0 {
+            words[self.words_per_row - 1] &= (1 << used_bits) - 1;
+        }
+    }
+
+    // Lines up the cell at col + dc with col, filling in the vacated cells according to the boundary
+    fn shift_row(&self, words: &[u64], dc: isize, boundary: Boundary) -> Vec<u64> {
+        let shift = dc.unsigned_abs();
+        if boundary == Boundary::Wrap {
+            let shift = dc.rem_euclid(self.width as isize) as usize;
+            let mut shifted = shift_right(words, shift);
+            let wrapped = shift_left(words, self.width - shift);
+            shifted.iter_mut().zip(wrapped).for_each(|(a, b)| *a |= b);
+            self.mask_row(&mut shifted);
+            return shifted;
+        }
+        let mut shifted = if dc >= 0 {
+            shift_right(words, shift)
+        } else {
+            shift_left(words, shift)
+        };
+        if boundary == Boundary::Occupied {
+            let vacated = if dc >= 0 {
+                get_range_mask(
+                    self.words_per_row,
+                    self.width.saturating_sub(shift),
+                    self.width,
+                )
+            } else {
+                get_range_mask(self.words_per_row, 0, shift.min(self.width))
+            };
+            shifted.iter_mut().zip(vacated).for_each(|(a, b)| *a |= b);
+        }
+        self.mask_row(&mut shifted);
+        shifted
+    }
+
+    // The row at row + dr, or a row of empty or occupied cells if that falls outside the grid
+    fn get_source_row(&self, row: usize, dr: isize, boundary: Boundary) -> Vec<u64> {
+        let source = row as isize + dr;
+        if boundary == Boundary::Wrap {
+            return self
+                .row(source.rem_euclid(self.height as isize) as usize)
+                .to_vec();
+        }
+        if source >= 0 && (source as usize) < self.height {
+            return self.row(source as usize).to_vec();
+        }
+        match boundary {
+            Boundary::Occupied => get_range_mask(self.words_per_row, 0, self.width),
+            Boundary::Empty | Boundary::Wrap => vec![0; self.words_per_row],
+        }
+    }
+
+    // Marks every roll whose occupied neighbour count is below the threshold. Counts are kept bit-sliced,
+    // with one plane per bit of the count, so every word adds 64 neighbours at once
+    pub fn get_accessible(&self, rule: &Rule) -> BitGrid {
+        let max_count = rule.offsets.len().max(rule.threshold as usize);
+        let plane_count = (usize::BITS - max_count.leading_zeros()) as usize;
+        let mut accessible = BitGrid {
+            words: vec![0; self.words.len()],
+            ..*self
+        };
+        for row in 0..self.height {
+            let mut planes = vec![vec![0u64; self.words_per_row]; plane_count];
+            for &(dr, dc) in rule.get_grid_offsets(self.height, self.width).iter() {
+                let neighbours = self.shift_row(
+                    &self.get_source_row(row, dr, rule.boundary),
+                    dc,
+                    rule.boundary,
+                );
+                for (i, word) in neighbours.into_iter().enumerate() {
+                    let mut carry = word;
+                    for plane in planes.iter_mut() {
+                        let sum = plane[i] ^ carry;
+                        carry &= plane[i];
+                        plane[i] = sum;
+                    }
+                }
+            }
+            let row_words =
+                &mut accessible.words[row * self.words_per_row..(row + 1) * self.words_per_row];
+            for (i, word) in row_words.iter_mut().enumerate() {
+                *word = self.row(row)[i] & count_below(&planes, i, rule.threshold);
+            }
+        }
+        accessible
+    }
+
+    pub fn remove(&mut self, cells: &BitGrid) {
+        self.words
+            .iter_mut()
+            .zip(&cells.words)
+            .for_each(|(a, b)| *a &= !b);
+    }
+}
+
+// Bits whose sliced count is below the threshold, comparing planes from the most significant down
+fn count_below(planes: &[Vec<u64>], i: usize, threshold: u32) -> u64 {
+    let mut below = 0;
+    let mut equal = u64::MAX;
+    for (bit, plane) in planes.iter().enumerate().rev() {
+        if threshold >> bit & 1 == 1 {
+            below |= equal & !plane[i];
+            equal &= plane[i];
+        } else {
+            equal &= !plane[i];
+        }
+    }
+    below
+}
+
+// Sets the bits for columns start..end
+fn get_range_mask(word_count: usize, start: usize, end: usize) -> Vec<u64> {
+    (0..word_count)
+        .map(|i| {
+            let low = (i * WORD_BITS).max(start);
+            let high = ((i + 1) * WORD_BITS).min(end);
+            match high.saturating_sub(low) {
+                0 => 0,
+                WORD_BITS => u64::MAX,
+                bit_count => ((1 << bit_count) - 1) << (low - i * WORD_BITS),
+            }
+        })
+        .collect()
+}
+
+// Moves every bit to a lower index across the whole row, filling with zeroes
+fn shift_right(words: &[u64], shift: usize) -> Vec<u64> {
+    let (word_shift, bit_shift) = (shift / WORD_BITS, shift % WORD_BITS);
+    (0..words.len())
+        .map(|i| {
+            let low = words
+                .get(i + word_shift)
+                .map_or(0, |word| word >> bit_shift);
+            let high = match bit_shift {
+                0 => 0,
+                _ => words
+                    .get(i + word_shift + 1)
+                    .map_or(0, |word| word << (WORD_BITS - bit_shift)),
+            };
+            low | high
+        })
+        .collect()
+}
+
+// Moves every bit to a higher index across the whole row, filling with zeroes
+fn shift_left(words: &[u64], shift: usize) -> Vec<u64> {
+    let (word_shift, bit_shift) = (shift / WORD_BITS, shift % WORD_BITS);
+    (0..words.len())
+        .map(|i| {
+            let Some(source) = i.checked_sub(word_shift) else {
+                return 0;
+            };
+            let high = words[source] << bit_shift;
+            let low = match (bit_shift, source.checked_sub(1)) {
+                (0, _) | (_, None) => 0,
+                (_, Some(previous)) => words[previous] >> (WORD_BITS - bit_shift),
+            };
+            high | low
+        })
+        .collect()
+}
+
+pub fn count_valid_rolls_in_bit_grid(grid: &BitGrid, rule: &Rule) -> u32 {
+    grid.get_accessible(rule).count_ones() as u32
+}
+
+// Removes every accessible roll in waves, like remove_until_no_more_valid_rolls does on the char grid
+pub fn remove_until_no_more_valid_rolls_in_bit_grid(grid: &BitGrid, rule: &Rule) -> usize {
+    let mut current_grid = grid.clone();
+    let mut total_removals = 0;
+    loop {
+        let accessible = current_grid.get_accessible(rule);
+        let removals = accessible.count_ones();
+        if removals == 0 {
+            break;
+        }
+        current_grid.remove(&accessible);
+        total_removals += removals;
+    }
+    total_removals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_grid, Neighbourhood};
+
+    #[test]
+    fn from_char_grid_works() {
+        let grid = crate::load_lines_into_grid("@.@\n.@.".to_string());
+        let bit_grid = BitGrid::from_char_grid(&grid, '@');
+        assert_eq!(bit_grid.words, vec![0b101, 0b010]);
+        assert_eq!(bit_grid.count_ones(), 3);
+    }
+
+    #[test]
+    fn shifts_work_across_words() {
+        let words = [1u64 << 63, 1];
+        assert_eq!(shift_left(&words, 1), vec![0, 3]);
+        assert_eq!(shift_right(&words, 1), vec![1 << 62 | 1 << 63, 0]);
+        assert_eq!(shift_right(&words, 64), vec![1, 0]);
+        assert_eq!(shift_left(&words, 64), vec![0, 1 << 63]);
+    }
+
+    #[test]
+    fn count_valid_rolls_in_bit_grid_works() {
+        let grid = crate::load_lines_into_grid(
+            "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.".to_string(),
+        );
+        let bit_grid = BitGrid::from_char_grid(&grid, '@');
+        assert_eq!(
+            count_valid_rolls_in_bit_grid(&bit_grid, &Rule::default()),
+            13
+        );
+        assert_eq!(
+            remove_until_no_more_valid_rolls_in_bit_grid(&bit_grid, &Rule::default()),
+            43
+        );
+    }
+
+    #[test]
+    fn bit_grid_matches_char_grid() {
+        let rules = [
+            Rule::default(),
+            Rule::new(Neighbourhood::Manhattan(1), 3, '@'),
+            Rule::new(Neighbourhood::Chebyshev(2), 13, '@'),
+            Rule::new(Neighbourhood::Manhattan(3), 30, '@'),
+        ];
+        for boundary in [Boundary::Empty, Boundary::Occupied, Boundary::Wrap] {
+            for rule in &rules {
+                let rule = rule.clone().with_boundary(boundary);
+                // Includes grids narrower than the neighbourhoods, which wrap onto themselves
+                for (seed, height, width) in [
+                    (1, 9, 7),
+                    (2, 20, 64),
+                    (3, 17, 130),
+                    (4, 5, 200),
+                    (5, 1, 9),
+                    (6, 2, 70),
+                    (7, 6, 2),
+                ] {
+                    let grid = generate_grid(seed, height, width);
+                    let bit_grid = BitGrid::from_char_grid(&grid, '@');
+                    assert_eq!(
+                        count_valid_rolls_in_bit_grid(&bit_grid, &rule),
+                        crate::count_valid_rolls_in_grid(&grid, &rule)
+                    );
+                    assert_eq!(
+                        remove_until_no_more_valid_rolls_in_bit_grid(&bit_grid, &rule),
+                        crate::remove_until_no_more_valid_rolls(&grid, &rule)
+                    );
+                }
+            }
+        }
+    }
+
+    // Run with `cargo test --release -p day-4 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_bit_grid() {
+        let grid = generate_grid(42, 1000, 1000);
+        let rule = Rule::default();
+        let start = std::time::Instant::now();
+        let bit_grid = BitGrid::from_char_grid(&grid, '@');
+        let removed = remove_until_no_more_valid_rolls_in_bit_grid(&bit_grid, &rule);
+        println!("bit grid: {:?}", start.elapsed());
+        let start = std::time::Instant::now();
+        assert_eq!(
+            crate::remove_until_no_more_valid_rolls(&grid, &rule),
+            removed
+        );
+        println!("char grid: {:?}", start.elapsed());
+    }
+}
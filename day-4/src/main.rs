@@ -1,4 +1,10 @@
-use std::fs;
+mod bitgrid;
+mod render;
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::{env, fs};
 
 fn load_lines_into_grid(contents: String) -> Vec<Vec<char>> {
     contents
@@ -7,57 +13,174 @@ fn load_lines_into_grid(contents: String) -> Vec<Vec<char>> {
         .collect::<Vec<Vec<char>>>()
 }
 
-fn count_at_char_in_adjacent_positions(
-    grid: &Vec<Vec<char>>,
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Neighbourhood {
+    // Every cell within r steps including diagonals, so a radius of 1 is the eight surrounding cells
+    Chebyshev(usize),
+    // Every cell within r orthogonal steps, so a radius of 1 is the four adjacent cells
+    Manhattan(usize),
+}
+
+impl Neighbourhood {
+    fn get_offsets(self) -> Vec<(isize, isize)> {
+        let radius = match self {
+            Neighbourhood::Chebyshev(radius) | Neighbourhood::Manhattan(radius) => radius as isize,
+        };
+        (-radius..=radius)
+            .flat_map(|dr| (-radius..=radius).map(move |dc| (dr, dc)))
+            .filter(|&(dr, dc)| (dr, dc) != (0, 0))
+            .filter(|&(dr, dc)| match self {
+                Neighbourhood::Chebyshev(_) => true,
+                Neighbourhood::Manhattan(_) => dr.abs() + dc.abs() <= radius,
+            })
+            .collect()
+    }
+}
+
+// Written over a roll when it is removed
+const REMOVED: char = '.';
+
+// How neighbours that fall outside the grid are treated
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Boundary {
+    Empty,
+    // Walls count as occupied neighbours, but can never be removed
+    Occupied,
+    // The grid wraps around into a torus, so every cell has a full neighbourhood
+    Wrap,
+}
+
+// Decides which cells hold a roll and when a roll is accessible to a forklift
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    offsets: Vec<(isize, isize)>,
+    // A roll is accessible when fewer than this many of its neighbours are occupied
+    threshold: u32,
+    occupied: char,
+    boundary: Boundary,
+}
+
+impl Rule {
+    fn new(neighbourhood: Neighbourhood, threshold: u32, occupied: char) -> Self {
+        // Removed rolls would otherwise still count as occupied, so removal would never finish
+        if occupied == REMOVED {
+            panic!(
+                "Occupied character cannot be {:?}, which marks removed rolls",
+                REMOVED
+            );
+        }
+        Rule {
+            offsets: neighbourhood.get_offsets(),
+            threshold,
+            occupied,
+            boundary: Boundary::Empty,
+        }
+    }
+
+    // Offsets to the distinct neighbours of a cell on a grid of the given size. Wrapping around a grid
+    // smaller than the neighbourhood would reach the same neighbour through several offsets, or the cell
+    // itself, so wrapped offsets are reduced to the grid and those that repeat or stay put are dropped
+    fn get_grid_offsets(&self, grid_height: usize, grid_width: usize) -> Cow<'_, [(isize, isize)]> {
+        if self.boundary != Boundary::Wrap {
+            return Cow::Borrowed(&self.offsets);
+        }
+        let mut offsets: Vec<(isize, isize)> = self
+            .offsets
+            .iter()
+            .map(|(dr, dc)| {
+                (
+                    dr.rem_euclid(grid_height as isize),
+                    dc.rem_euclid(grid_width as isize),
+                )
+            })
+            .filter(|&offset| offset != (0, 0))
+            .collect();
+        offsets.sort();
+        offsets.dedup();
+        Cow::Owned(offsets)
+    }
+
+    fn with_boundary(self, boundary: Boundary) -> Self {
+        Rule { boundary, ..self }
+    }
+
+    fn is_accessible(&self, adjacent_count: u32) -> bool {
+        adjacent_count < self.threshold
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::new(Neighbourhood::Chebyshev(1), 4, '@')
+    }
+}
+
+// Positions of the neighbours of a cell that fall inside the grid, wrapping around it if the boundary does
+fn get_adjacent_positions<'a>(
+    rule: &'a Rule,
+    grid_height: usize,
+    grid_width: usize,
+    row: usize,
+    col: usize,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let offsets = rule.get_grid_offsets(grid_height, grid_width);
+    (0..offsets.len()).filter_map(move |i| {
+        let (dr, dc) = offsets[i];
+        if rule.boundary == Boundary::Wrap {
+            // Wrapped offsets are never negative
+            let new_row = (row + dr as usize) % grid_height;
+            let new_col = (col + dc as usize) % grid_width;
+            return Some((new_row, new_col));
+        }
+        let new_row = row.checked_add_signed(dr)?;
+        let new_col = col.checked_add_signed(dc)?;
+        (new_row < grid_height && new_col < grid_width).then_some((new_row, new_col))
+    })
+}
+
+// Neighbours that lie beyond the edge of the grid and count as occupied
+fn count_wall_positions(
+    rule: &Rule,
+    grid_height: usize,
+    grid_width: usize,
     row: usize,
     col: usize,
 ) -> u32 {
-    let grid_width = grid[0].len() as isize;
-    let grid_height = grid.len() as isize;
-    let directions = vec![
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-    let mut count = 0;
-    for (dr, dc) in directions {
-        let new_row = row as isize + dr;
-        let new_col = col as isize + dc;
-        if new_row >= 0
-            && new_row < grid_height
-            && new_col >= 0
-            && new_col < grid_width
-        {
-        if grid[new_row as usize][new_col as usize] == '@' {
-                count += 1;
-            }
+    match rule.boundary {
+        Boundary::Occupied => {
+            let inside = get_adjacent_positions(rule, grid_height, grid_width, row, col).count();
+            (rule.offsets.len() - inside) as u32
         }
+        Boundary::Empty | Boundary::Wrap => 0,
     }
-    count
 }
 
-fn remove_roll_at_position(
-    grid: &mut Vec<Vec<char>>,
+fn count_at_char_in_adjacent_positions(
+    grid: &[Vec<char>],
     row: usize,
     col: usize,
-) {
-    grid[row][col] = '.';
+    rule: &Rule,
+) -> u32 {
+    let (grid_height, grid_width) = (grid.len(), grid[0].len());
+    let occupied_neighbours = get_adjacent_positions(rule, grid_height, grid_width, row, col)
+        .filter(|&(new_row, new_col)| grid[new_row][new_col] == rule.occupied)
+        .count() as u32;
+    occupied_neighbours + count_wall_positions(rule, grid_height, grid_width, row, col)
+}
+
+fn remove_roll_at_position(grid: &mut [Vec<char>], row: usize, col: usize) {
+    grid[row][col] = REMOVED;
 }
 
-fn count_valid_rolls_in_grid(grid: &Vec<Vec<char>>) -> u32 {
+fn count_valid_rolls_in_grid(grid: &[Vec<char>], rule: &Rule) -> u32 {
     let mut valid_positions = 0;
     for row in 0..grid.len() {
         for col in 0..grid[0].len() {
-            if grid[row][col] != '@' {
+            if grid[row][col] != rule.occupied {
                 continue;
             }
-            let adjacent_at_count = count_at_char_in_adjacent_positions(&grid, row, col);
-            if adjacent_at_count < 4 {
+            let adjacent_at_count = count_at_char_in_adjacent_positions(grid, row, col, rule);
+            if rule.is_accessible(adjacent_at_count) {
                 valid_positions += 1;
             }
         }
@@ -66,16 +189,16 @@ fn count_valid_rolls_in_grid(grid: &Vec<Vec<char>>) -> u32 {
 }
 
 // Returns a new grid with valid rolls removed and the count of removals
-fn remove_valid_rolls_in_grid(grid: &Vec<Vec<char>>) -> (Vec<Vec<char>>, usize) {
-    let mut new_grid = grid.clone();
+fn remove_valid_rolls_in_grid(grid: &[Vec<char>], rule: &Rule) -> (Vec<Vec<char>>, usize) {
+    let mut new_grid = grid.to_vec();
     let mut removals = 0;
     for row in 0..grid.len() {
         for col in 0..grid[0].len() {
-            if grid[row][col] != '@' {
+            if grid[row][col] != rule.occupied {
                 continue;
             }
-            let adjacent_at_count = count_at_char_in_adjacent_positions(&grid, row, col);
-            if adjacent_at_count < 4 {
+            let adjacent_at_count = count_at_char_in_adjacent_positions(grid, row, col, rule);
+            if rule.is_accessible(adjacent_at_count) {
                 remove_roll_at_position(&mut new_grid, row, col);
                 removals += 1;
             }
@@ -85,13 +208,11 @@ fn remove_valid_rolls_in_grid(grid: &Vec<Vec<char>>) -> (Vec<Vec<char>>, usize)
 }
 
 // Removes valid rolls repeatedly until no more can be removed
-fn remove_until_no_more_valid_rolls(
-    grid: &Vec<Vec<char>>,
-) -> usize {
-    let mut current_grid = grid.clone();
+fn remove_until_no_more_valid_rolls(grid: &[Vec<char>], rule: &Rule) -> usize {
+    let mut current_grid = grid.to_vec();
     let mut total_removals = 0;
     loop {
-        let (new_grid, removals) = remove_valid_rolls_in_grid(&current_grid);
+        let (new_grid, removals) = remove_valid_rolls_in_grid(&current_grid, rule);
         if removals == 0 {
             break;
         }
@@ -101,19 +222,251 @@ fn remove_until_no_more_valid_rolls(
     total_removals
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RemovalSemantics {
+    // Every accessible roll in a wave is removed at once, as in remove_valid_rolls_in_grid
+    Waves,
+    // Each roll is removed as soon as it becomes accessible, affecting rolls checked after it
+    Immediate,
+}
+
+fn remove_rolls_with_worklist(
+    grid: &[Vec<char>],
+    rule: &Rule,
+    semantics: RemovalSemantics,
+) -> usize {
+    get_removal_waves(grid, rule, semantics)
+        .iter()
+        .map(|wave| wave.len())
+        .sum()
+}
+
+// Lists the rolls removed in each wave, in row-major order. Keeps a neighbour count per roll and only
+// re-examines the neighbours of removed rolls, rather than rescanning the whole grid after every wave
+fn get_removal_waves(
+    grid: &[Vec<char>],
+    rule: &Rule,
+    semantics: RemovalSemantics,
+) -> Vec<Vec<(usize, usize)>> {
+    let grid_height = grid.len();
+    let grid_width = grid.first().map_or(0, |line| line.len());
+    let mut present: Vec<Vec<bool>> = grid
+        .iter()
+        .map(|line| line.iter().map(|c| *c == rule.occupied).collect())
+        .collect();
+    let mut neighbour_counts: Vec<Vec<u32>> = (0..grid_height)
+        .map(|row| {
+            (0..grid_width)
+                .map(|col| count_at_char_in_adjacent_positions(grid, row, col, rule))
+                .collect()
+        })
+        .collect();
+    let mut queue: VecDeque<(usize, usize)> = (0..grid_height)
+        .flat_map(|row| (0..grid_width).map(move |col| (row, col)))
+        .filter(|&(row, col)| present[row][col] && rule.is_accessible(neighbour_counts[row][col]))
+        .collect();
+
+    let mut waves = vec![];
+    while !queue.is_empty() {
+        let batch: Vec<(usize, usize)> = match semantics {
+            RemovalSemantics::Waves => queue.drain(..).collect(),
+            RemovalSemantics::Immediate => queue.pop_front().into_iter().collect(),
+        };
+        // Remove the whole batch before updating counts, so a wave only sees the grid as it was before it
+        let mut removed: Vec<(usize, usize)> = batch
+            .into_iter()
+            .filter(|&(row, col)| std::mem::replace(&mut present[row][col], false))
+            .collect();
+        removed.sort();
+        for &(row, col) in &removed {
+            for (new_row, new_col) in
+                get_adjacent_positions(rule, grid_height, grid_width, row, col)
+            {
+                neighbour_counts[new_row][new_col] -= 1;
+                // Only queue a roll the moment its count drops below the threshold, so it is queued once
+                if present[new_row][new_col]
+                    && neighbour_counts[new_row][new_col] + 1 == rule.threshold
+                {
+                    queue.push_back((new_row, new_col));
+                }
+            }
+        }
+        waves.push(removed);
+    }
+    waves
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RemovalDepth {
+    Wave(usize),
+    Never,
+}
+
+// For every roll, the wave in which remove_until_no_more_valid_rolls removes it. Cells without a roll are None
+fn get_removal_depths(grid: &[Vec<char>], rule: &Rule) -> Vec<Vec<Option<RemovalDepth>>> {
+    let mut depths: Vec<Vec<Option<RemovalDepth>>> = grid
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|c| (*c == rule.occupied).then_some(RemovalDepth::Never))
+                .collect()
+        })
+        .collect();
+    let waves = get_removal_waves(grid, rule, RemovalSemantics::Waves);
+    for (i, wave) in waves.iter().enumerate() {
+        for &(row, col) in wave {
+            depths[row][col] = Some(RemovalDepth::Wave(i));
+        }
+    }
+    depths
+}
+
+// Number of rolls removed in each wave, followed by the number that are never removed
+fn get_removal_depth_histogram(depths: &[Vec<Option<RemovalDepth>>]) -> (Vec<usize>, usize) {
+    let mut per_wave = vec![];
+    let mut never = 0;
+    for depth in depths.iter().flatten().flatten() {
+        match depth {
+            RemovalDepth::Wave(i) => {
+                if per_wave.len() <= *i {
+                    per_wave.resize(i + 1, 0);
+                }
+                per_wave[*i] += 1;
+            }
+            RemovalDepth::Never => never += 1,
+        }
+    }
+    (per_wave, never)
+}
+
+// Reads `key=value` options such as `neighbourhood=manhattan:2 threshold=3 occupied=#`
+fn parse_rule(options: &[String]) -> Rule {
+    let default_rule = Rule::default();
+    let mut neighbourhood = Neighbourhood::Chebyshev(1);
+    let mut threshold = default_rule.threshold;
+    let mut occupied = default_rule.occupied;
+    let mut boundary = default_rule.boundary;
+    for option in options {
+        match option.split_once('=') {
+            Some(("neighbourhood", value)) => {
+                let (kind, radius) = value.split_once(':').unwrap_or((value, "1"));
+                let radius = radius.parse().expect("Failed to parse radius");
+                neighbourhood = match kind {
+                    "chebyshev" => Neighbourhood::Chebyshev(radius),
+                    "manhattan" => Neighbourhood::Manhattan(radius),
+                    _ => panic!("Unknown neighbourhood: {}", kind),
+                };
+            }
+            Some(("threshold", value)) => {
+                threshold = value.parse().expect("Failed to parse threshold");
+            }
+            Some(("occupied", value)) => {
+                occupied = value.chars().next().expect("Occupied character is empty");
+            }
+            Some(("boundary", value)) => {
+                boundary = match value {
+                    "empty" => Boundary::Empty,
+                    "occupied" => Boundary::Occupied,
+                    "wrap" => Boundary::Wrap,
+                    _ => panic!("Unknown boundary: {}", value),
+                };
+            }
+            _ => panic!("Unknown option: {}", option),
+        }
+    }
+    Rule::new(neighbourhood, threshold, occupied).with_boundary(boundary)
+}
+
 fn main() {
+    // Optionally pass the removal algorithm to use: `waves` (default), `immediate`, `rescan` or `bits`,
+    // or `animate` / `svg` / `depth` to show the waves, followed by any rule options
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mode = args.first().map_or("waves", |arg| arg.as_str());
+    let rule = parse_rule(args.get(1..).unwrap_or_default());
     match fs::read_to_string("./day-4/assets/input.txt") {
         Ok(contents) => {
             let grid = load_lines_into_grid(contents);
-            let total_removed = remove_until_no_more_valid_rolls(&grid);
+            let total_removed = match mode {
+                "waves" => remove_rolls_with_worklist(&grid, &rule, RemovalSemantics::Waves),
+                "immediate" => {
+                    remove_rolls_with_worklist(&grid, &rule, RemovalSemantics::Immediate)
+                }
+                "rescan" => remove_until_no_more_valid_rolls(&grid, &rule),
+                "bits" => {
+                    let bit_grid = bitgrid::BitGrid::from_char_grid(&grid, rule.occupied);
+                    println!(
+                        "valid_rolls: {}",
+                        bitgrid::count_valid_rolls_in_bit_grid(&bit_grid, &rule)
+                    );
+                    let total_removed =
+                        bitgrid::remove_until_no_more_valid_rolls_in_bit_grid(&bit_grid, &rule);
+                    println!("total_removed: {}", total_removed);
+                    return;
+                }
+                "animate" => {
+                    let waves = get_removal_waves(&grid, &rule, RemovalSemantics::Waves);
+                    let frames = render::render_wave_frames(&grid, &waves);
+                    render::animate_in_terminal(&frames, Duration::from_millis(200));
+                    return;
+                }
+                "svg" => {
+                    let waves = get_removal_waves(&grid, &rule, RemovalSemantics::Waves);
+                    println!("{}", render::export_svg(&grid, &waves, &rule, 0.5));
+                    return;
+                }
+                "depth" => {
+                    let depths = get_removal_depths(&grid, &rule);
+                    println!("{}", render::render_depth_heat_map(&depths));
+                    let (per_wave, never) = get_removal_depth_histogram(&depths);
+                    println!("{}", render::render_depth_histogram(&per_wave, never));
+                    return;
+                }
+                _ => panic!("Unknown mode: {}", mode),
+            };
+            println!("valid_rolls: {}", count_valid_rolls_in_grid(&grid, &rule));
             println!("total_removed: {}", total_removed);
         }
         Err(e) => eprintln!("Error reading file: {}", e),
     }
 }
 
+// Pseudo-random grid with roughly 70% of cells holding a roll
+#[cfg(test)]
+pub(crate) fn generate_grid(seed: u64, height: usize, width: usize) -> Vec<Vec<char>> {
+    let mut state = seed;
+    (0..height)
+        .map(|_| {
+            (0..width)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    if (state >> 33) % 10 < 7 {
+                        '@'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{generate_grid, Boundary, Neighbourhood, Rule};
+
+    const EXAMPLE: &str = "..@@.@@@@.
+@@@.@.@.@@
+@@@@@.@.@@
+@.@@@@..@.
+@@.@@@@.@@
+.@@@@@@@.@
+.@.@.@.@@@
+@.@@@.@@@@
+.@@@@@@@@.
+@.@.@@@.@.";
+
     #[test]
     fn load_lines_into_grid_works() {
         let input = "abc\ndef\nghi";
@@ -132,7 +485,7 @@ mod tests {
             vec!['b', '@', 'c'],
             vec!['@', 'd', '@'],
         ];
-        let count = super::count_at_char_in_adjacent_positions(&grid, 1, 1);
+        let count = super::count_at_char_in_adjacent_positions(&grid, 1, 1, &Rule::default());
         assert_eq!(count, 4);
     }
 
@@ -143,7 +496,7 @@ mod tests {
             vec!['b', '@', 'c'],
             vec!['@', 'd', '@'],
         ];
-        let count = super::count_at_char_in_adjacent_positions(&grid, 0, 0);
+        let count = super::count_at_char_in_adjacent_positions(&grid, 0, 0, &Rule::default());
         assert_eq!(count, 1);
     }
 
@@ -160,7 +513,7 @@ mod tests {
 .@@@@@@@@.
 @.@.@@@.@.";
         let grid = super::load_lines_into_grid(str.to_string());
-        let count = super::count_valid_rolls_in_grid(&grid);
+        let count = super::count_valid_rolls_in_grid(&grid, &Rule::default());
         assert_eq!(count, 13);
     }
 
@@ -202,7 +555,7 @@ mod tests {
 .@@@@@@@@.
 ....@@@...";
         let grid = super::load_lines_into_grid(str.to_string());
-        let new_grid = super::remove_valid_rolls_in_grid(&grid);
+        let new_grid = super::remove_valid_rolls_in_grid(&grid, &Rule::default());
         let expected_grid = super::load_lines_into_grid(output_str.to_string());
         assert_eq!(new_grid, (expected_grid, 13));
     }
@@ -220,7 +573,350 @@ mod tests {
 .@@@@@@@@.
 @.@.@@@.@.";
         let grid = super::load_lines_into_grid(str.to_string());
-        let total_removed = super::remove_until_no_more_valid_rolls(&grid);
+        let total_removed = super::remove_until_no_more_valid_rolls(&grid, &Rule::default());
         assert_eq!(total_removed, 43);
     }
+
+    #[test]
+    fn remove_rolls_with_worklist_matches_waves() {
+        let grid = super::load_lines_into_grid(EXAMPLE.to_string());
+        assert_eq!(
+            super::remove_rolls_with_worklist(
+                &grid,
+                &Rule::default(),
+                super::RemovalSemantics::Waves
+            ),
+            43
+        );
+    }
+
+    #[test]
+    fn remove_rolls_with_worklist_works_immediately() {
+        let grid = super::load_lines_into_grid(EXAMPLE.to_string());
+        assert_eq!(
+            super::remove_rolls_with_worklist(
+                &grid,
+                &Rule::default(),
+                super::RemovalSemantics::Immediate
+            ),
+            43
+        );
+    }
+
+    #[test]
+    fn remove_rolls_with_worklist_matches_rescan_on_generated_grids() {
+        for seed in 1..20u64 {
+            let grid = generate_grid(seed, 30, 40);
+            let expected = super::remove_until_no_more_valid_rolls(&grid, &Rule::default());
+            for semantics in [
+                super::RemovalSemantics::Waves,
+                super::RemovalSemantics::Immediate,
+            ] {
+                assert_eq!(
+                    super::remove_rolls_with_worklist(&grid, &Rule::default(), semantics),
+                    expected
+                );
+            }
+        }
+    }
+
+    // Run with `cargo test --release -p day-4 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_remove_rolls() {
+        let grid = generate_grid(42, 1000, 1000);
+        let start = std::time::Instant::now();
+        let rescanned = super::remove_until_no_more_valid_rolls(&grid, &Rule::default());
+        println!("rescan: {:?}", start.elapsed());
+        for semantics in [
+            super::RemovalSemantics::Waves,
+            super::RemovalSemantics::Immediate,
+        ] {
+            let start = std::time::Instant::now();
+            let removed = super::remove_rolls_with_worklist(&grid, &Rule::default(), semantics);
+            println!("worklist {:?}: {:?}", semantics, start.elapsed());
+            assert_eq!(removed, rescanned);
+        }
+    }
+
+    #[test]
+    fn neighbourhood_offsets_work() {
+        assert_eq!(Neighbourhood::Chebyshev(1).get_offsets().len(), 8);
+        assert_eq!(Neighbourhood::Chebyshev(2).get_offsets().len(), 24);
+        assert_eq!(
+            Neighbourhood::Manhattan(1).get_offsets(),
+            vec![(-1, 0), (0, -1), (0, 1), (1, 0)]
+        );
+        assert_eq!(Neighbourhood::Manhattan(2).get_offsets().len(), 12);
+    }
+
+    #[test]
+    fn count_valid_rolls_in_grid_works_with_four_neighbourhood() {
+        let grid = super::load_lines_into_grid(EXAMPLE.to_string());
+        let rule = Rule::new(Neighbourhood::Manhattan(1), 4, '@');
+        let at_count = EXAMPLE.chars().filter(|c| *c == '@').count() as u32;
+        // Every roll with all four orthogonal neighbours occupied is blocked
+        let blocked = (1..9)
+            .flat_map(|row| (1..9).map(move |col| (row, col)))
+            .filter(|&(row, col)| {
+                grid[row][col] == '@'
+                    && grid[row - 1][col] == '@'
+                    && grid[row + 1][col] == '@'
+                    && grid[row][col - 1] == '@'
+                    && grid[row][col + 1] == '@'
+            })
+            .count() as u32;
+        assert_eq!(
+            super::count_valid_rolls_in_grid(&grid, &rule),
+            at_count - blocked
+        );
+    }
+
+    #[test]
+    fn count_valid_rolls_in_grid_works_with_other_threshold_and_char() {
+        let grid = super::load_lines_into_grid(EXAMPLE.replace('@', "#"));
+        let rule = Rule::new(Neighbourhood::Chebyshev(1), 4, '#');
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &rule), 13);
+        let rule = Rule::new(Neighbourhood::Chebyshev(1), 9, '#');
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &rule), 71);
+        let rule = Rule::new(Neighbourhood::Chebyshev(1), 0, '#');
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &rule), 0);
+    }
+
+    #[test]
+    fn remove_rolls_with_worklist_matches_rescan_for_other_rules() {
+        let rules = [
+            Rule::new(Neighbourhood::Manhattan(1), 3, '@'),
+            Rule::new(Neighbourhood::Manhattan(2), 7, '@'),
+            Rule::new(Neighbourhood::Chebyshev(2), 12, '@'),
+            Rule::new(Neighbourhood::Chebyshev(1), 0, '@'),
+        ];
+        for seed in 1..5u64 {
+            let grid = generate_grid(seed, 25, 25);
+            for rule in &rules {
+                let expected = super::remove_until_no_more_valid_rolls(&grid, rule);
+                for semantics in [
+                    super::RemovalSemantics::Waves,
+                    super::RemovalSemantics::Immediate,
+                ] {
+                    assert_eq!(
+                        super::remove_rolls_with_worklist(&grid, rule, semantics),
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_rule_works() {
+        assert_eq!(super::parse_rule(&[]), Rule::default());
+        let options = ["neighbourhood=manhattan:2", "threshold=3", "occupied=#"].map(String::from);
+        assert_eq!(
+            super::parse_rule(&options),
+            Rule::new(Neighbourhood::Manhattan(2), 3, '#')
+        );
+        let options = ["boundary=wrap"].map(String::from);
+        assert_eq!(
+            super::parse_rule(&options),
+            Rule::default().with_boundary(Boundary::Wrap)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Occupied character cannot be '.', which marks removed rolls")]
+    fn parse_rule_rejects_removed_marker() {
+        super::parse_rule(&["occupied=.".to_string()]);
+    }
+
+    #[test]
+    fn get_removal_waves_works() {
+        let grid = super::load_lines_into_grid(EXAMPLE.to_string());
+        let waves =
+            super::get_removal_waves(&grid, &Rule::default(), super::RemovalSemantics::Waves);
+        let wave_sizes: Vec<usize> = waves.iter().map(|wave| wave.len()).collect();
+        assert_eq!(wave_sizes, vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+        assert_eq!(waves[0][..3], [(0, 2), (0, 3), (0, 5)]);
+    }
+
+    #[test]
+    fn empty_grid_removes_nothing() {
+        let grid = super::load_lines_into_grid(String::new());
+        let rule = Rule::default();
+        for semantics in [
+            super::RemovalSemantics::Waves,
+            super::RemovalSemantics::Immediate,
+        ] {
+            assert_eq!(
+                super::remove_rolls_with_worklist(&grid, &rule, semantics),
+                0
+            );
+        }
+        assert_eq!(super::remove_until_no_more_valid_rolls(&grid, &rule), 0);
+        let bit_grid = crate::bitgrid::BitGrid::from_char_grid(&grid, rule.occupied);
+        assert_eq!(
+            crate::bitgrid::remove_until_no_more_valid_rolls_in_bit_grid(&bit_grid, &rule),
+            0
+        );
+        assert!(
+            crate::render::export_svg(&grid, &[], &rule, 0.5).contains("width=\"0\" height=\"0\"")
+        );
+        assert!(super::get_removal_depths(&grid, &rule).is_empty());
+    }
+
+    #[test]
+    fn get_removal_waves_matches_remove_valid_rolls_in_grid() {
+        let grid = generate_grid(7, 20, 20);
+        let rule = Rule::default();
+        let waves = super::get_removal_waves(&grid, &rule, super::RemovalSemantics::Waves);
+        let mut current_grid = grid.clone();
+        for wave in waves {
+            let (new_grid, removals) = super::remove_valid_rolls_in_grid(&current_grid, &rule);
+            assert_eq!(removals, wave.len());
+            for (row, col) in wave {
+                super::remove_roll_at_position(&mut current_grid, row, col);
+            }
+            assert_eq!(current_grid, new_grid);
+        }
+        assert_eq!(super::remove_valid_rolls_in_grid(&current_grid, &rule).1, 0);
+    }
+
+    #[test]
+    fn count_at_char_in_adjacent_positions_works_with_occupied_boundary() {
+        let grid = vec![
+            vec!['@', 'a', '@'],
+            vec!['b', '@', 'c'],
+            vec!['@', 'd', '@'],
+        ];
+        let rule = Rule::default().with_boundary(Boundary::Occupied);
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 0, 0, &rule),
+            6
+        );
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 0, 1, &rule),
+            6
+        );
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 1, 1, &rule),
+            4
+        );
+    }
+
+    #[test]
+    fn count_at_char_in_adjacent_positions_works_with_wrap_boundary() {
+        let grid = vec![
+            vec!['@', '.', '.', '.'],
+            vec!['.', '.', '.', '@'],
+            vec!['.', '.', '.', '.'],
+            vec!['@', '.', '.', '@'],
+        ];
+        let rule = Rule::default().with_boundary(Boundary::Wrap);
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 0, 0, &rule),
+            3
+        );
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 3, 3, &rule),
+            2
+        );
+    }
+
+    #[test]
+    fn occupied_boundary_keeps_edge_rolls() {
+        // With walls counting as neighbours, a solid block can never be eroded
+        let grid = super::load_lines_into_grid("@@@@\n@@@@\n@@@@".to_string());
+        let rule = Rule::default().with_boundary(Boundary::Occupied);
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &rule), 0);
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &Rule::default()), 4);
+        assert_eq!(super::remove_until_no_more_valid_rolls(&grid, &rule), 0);
+    }
+
+    #[test]
+    fn boundary_modes_change_removals_on_example() {
+        let grid = super::load_lines_into_grid(EXAMPLE.to_string());
+        let occupied = Rule::default().with_boundary(Boundary::Occupied);
+        let wrap = Rule::default().with_boundary(Boundary::Wrap);
+        assert!(super::remove_until_no_more_valid_rolls(&grid, &occupied) < 43);
+        assert!(super::count_valid_rolls_in_grid(&grid, &wrap) < 13);
+    }
+
+    #[test]
+    fn wrap_boundary_counts_each_neighbour_once_on_narrow_grids() {
+        let wrap = Rule::default().with_boundary(Boundary::Wrap);
+        // A single row wraps onto itself, leaving each roll with only its left and right neighbours
+        let grid = super::load_lines_into_grid("@@@@@".to_string());
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 0, 0, &wrap),
+            2
+        );
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &wrap), 5);
+        // Two rows give five distinct neighbours rather than eight
+        let grid = super::load_lines_into_grid("@@@@@\n@@@@@".to_string());
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 1, 2, &wrap),
+            5
+        );
+        let rule = Rule::new(Neighbourhood::Chebyshev(1), 6, '@').with_boundary(Boundary::Wrap);
+        assert_eq!(super::count_valid_rolls_in_grid(&grid, &rule), 10);
+        // A single cell is no neighbour of itself
+        let grid = super::load_lines_into_grid("@".to_string());
+        assert_eq!(
+            super::count_at_char_in_adjacent_positions(&grid, 0, 0, &wrap),
+            0
+        );
+    }
+
+    #[test]
+    fn remove_rolls_with_worklist_matches_rescan_for_every_boundary() {
+        for boundary in [Boundary::Empty, Boundary::Occupied, Boundary::Wrap] {
+            for rule in [
+                Rule::default().with_boundary(boundary),
+                Rule::new(Neighbourhood::Manhattan(2), 6, '@').with_boundary(boundary),
+            ] {
+                for seed in 1..5u64 {
+                    let grid = generate_grid(seed, 15, 12);
+                    let expected = super::remove_until_no_more_valid_rolls(&grid, &rule);
+                    let waves =
+                        super::get_removal_waves(&grid, &rule, super::RemovalSemantics::Waves);
+                    assert_eq!(waves.iter().map(|wave| wave.len()).sum::<usize>(), expected);
+                    assert_eq!(
+                        waves.first().map_or(0, |wave| wave.len()) as u32,
+                        super::count_valid_rolls_in_grid(&grid, &rule)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn get_removal_depths_works() {
+        let grid = super::load_lines_into_grid("@@@@@\n@@@@@\n@@@@@\n.....".to_string());
+        let depths = super::get_removal_depths(&grid, &Rule::default());
+        use super::RemovalDepth::{Never, Wave};
+        // The block erodes inwards from its corners
+        assert_eq!(
+            depths[0],
+            vec![
+                Some(Wave(0)),
+                Some(Wave(2)),
+                Some(Wave(3)),
+                Some(Wave(2)),
+                Some(Wave(0))
+            ]
+        );
+        assert_eq!(depths[3], vec![None; 5]);
+        let threshold_rule = Rule::new(super::Neighbourhood::Chebyshev(1), 3, '@');
+        let depths = super::get_removal_depths(&grid, &threshold_rule);
+        assert_eq!(depths[1][2], Some(Never));
+    }
+
+    #[test]
+    fn get_removal_depth_histogram_works() {
+        let grid = super::load_lines_into_grid(EXAMPLE.to_string());
+        let depths = super::get_removal_depths(&grid, &Rule::default());
+        let (per_wave, never) = super::get_removal_depth_histogram(&depths);
+        assert_eq!(per_wave, vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+        assert_eq!(never, 71 - 43);
+    }
 }
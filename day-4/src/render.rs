@@ -0,0 +1,175 @@
+use crate::{remove_roll_at_position, RemovalDepth, Rule};
+use std::fmt::Write;
+use std::thread;
+use std::time::Duration;
+
+// One frame for the starting grid and then one per wave, with the rolls removed in that wave marked as `x`
+pub fn render_wave_frames(grid: &[Vec<char>], waves: &[Vec<(usize, usize)>]) -> Vec<String> {
+    let mut current_grid = grid.to_vec();
+    let mut frames = vec![render_grid(&current_grid)];
+    for wave in waves {
+        for &(row, col) in wave {
+            current_grid[row][col] = 'x';
+        }
+        frames.push(render_grid(&current_grid));
+        for &(row, col) in wave {
+            remove_roll_at_position(&mut current_grid, row, col);
+        }
+    }
+    frames
+}
+
+fn render_grid(grid: &[Vec<char>]) -> String {
+    grid.iter()
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn animate_in_terminal(frames: &[String], delay: Duration) {
+    for (i, frame) in frames.iter().enumerate() {
+        // Clear the screen and move the cursor to the top left before drawing each frame
+        print!("\x1b[2J\x1b[H");
+        println!("{}\nwave {}/{}", frame, i, frames.len() - 1);
+        thread::sleep(delay);
+    }
+}
+
+// Draws every roll as a square that turns red in the wave it is removed, then disappears
+pub fn export_svg(
+    grid: &[Vec<char>],
+    waves: &[Vec<(usize, usize)>],
+    rule: &Rule,
+    seconds_per_wave: f64,
+) -> String {
+    const CELL_SIZE: usize = 8;
+    let width = grid.first().map_or(0, |line| line.len());
+    let mut removal_wave = vec![vec![None; width]; grid.len()];
+    for (i, wave) in waves.iter().enumerate() {
+        for &(row, col) in wave {
+            removal_wave[row][col] = Some(i);
+        }
+    }
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        width * CELL_SIZE,
+        grid.len() * CELL_SIZE
+    );
+    for (row, line) in grid.iter().enumerate() {
+        for (col, cell) in line.iter().enumerate() {
+            if *cell != rule.occupied {
+                continue;
+            }
+            let (x, y) = (col * CELL_SIZE, row * CELL_SIZE);
+            match removal_wave[row][col] {
+                Some(i) => {
+                    let begin = i as f64 * seconds_per_wave;
+                    writeln!(
+                        svg,
+                        "<rect x=\"{x}\" y=\"{y}\" width=\"{CELL_SIZE}\" height=\"{CELL_SIZE}\" fill=\"saddlebrown\">\
+                         <set attributeName=\"fill\" to=\"red\" begin=\"{begin}s\" fill=\"freeze\"/>\
+                         <set attributeName=\"visibility\" to=\"hidden\" begin=\"{}s\" fill=\"freeze\"/></rect>",
+                        begin + seconds_per_wave
+                    )
+                    .unwrap();
+                }
+                None => {
+                    writeln!(
+                        svg,
+                        "<rect x=\"{x}\" y=\"{y}\" width=\"{CELL_SIZE}\" height=\"{CELL_SIZE}\" fill=\"saddlebrown\"/>"
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// Shows the wave each roll is removed in as 0-9 then a-z, `+` for later waves and `@` for rolls never removed
+pub fn render_depth_heat_map(depths: &[Vec<Option<RemovalDepth>>]) -> String {
+    depths
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|depth| match depth {
+                    None => '.',
+                    Some(RemovalDepth::Never) => '@',
+                    Some(RemovalDepth::Wave(i)) => char::from_digit(*i as u32, 36).unwrap_or('+'),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn render_depth_histogram(per_wave: &[usize], never: usize) -> String {
+    const MAX_BAR_WIDTH: usize = 50;
+    let largest = per_wave
+        .iter()
+        .copied()
+        .chain([never])
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let bar = |count: usize| "#".repeat((count * MAX_BAR_WIDTH).div_ceil(largest));
+    per_wave
+        .iter()
+        .enumerate()
+        .map(|(i, count)| format!("wave {:>4}: {:>6} {}", i, count, bar(*count)))
+        .chain([format!("never    : {:>6} {}", never, bar(never))])
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_wave_frames_works() {
+        let grid = crate::load_lines_into_grid("@@@\n@@@".to_string());
+        let waves = vec![vec![(0, 0), (0, 2), (1, 0), (1, 2)], vec![(0, 1), (1, 1)]];
+        let frames = render_wave_frames(&grid, &waves);
+        assert_eq!(frames, vec!["@@@\n@@@", "x@x\nx@x", ".x.\n.x."]);
+    }
+
+    #[test]
+    fn export_svg_works() {
+        let grid = crate::load_lines_into_grid("@.\n.@".to_string());
+        let waves = vec![vec![(0, 0)]];
+        let svg = export_svg(&grid, &waves, &Rule::default(), 0.5);
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"16\" height=\"16\">"));
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert_eq!(svg.matches("visibility").count(), 1);
+        assert!(svg.contains("begin=\"0.5s\""));
+        assert!(svg.ends_with("</svg>"));
+    }
+
+    #[test]
+    fn render_depth_heat_map_works() {
+        let depths = vec![
+            vec![Some(RemovalDepth::Wave(0)), None],
+            vec![Some(RemovalDepth::Wave(11)), Some(RemovalDepth::Never)],
+            vec![Some(RemovalDepth::Wave(40)), None],
+        ];
+        assert_eq!(render_depth_heat_map(&depths), "0.\nb@\n+.");
+    }
+
+    #[test]
+    fn render_depth_histogram_works() {
+        let histogram = render_depth_histogram(&[4, 2], 1);
+        assert_eq!(
+            histogram,
+            format!(
+                "wave    0:      4 {}\nwave    1:      2 {}\nnever    :      1 {}",
+                "#".repeat(50),
+                "#".repeat(25),
+                "#".repeat(13)
+            )
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Longest power checked_pow will compute, as a large enough exponent would otherwise take gigabytes of limbs
+const MAX_POW_BITS: u64 = 1 << 16;
+
+// Arbitrary-precision unsigned integer, stored as base 2^32 limbs from least to most significant,
+// with no trailing zero limbs so that zero is an empty vector
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { limbs: vec![] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalise(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.is_empty() {
+            return None;
+        }
+        s.chars().try_fold(BigUint::zero(), |acc, c| {
+            let digit = c.to_digit(10)?;
+            Some(acc.mul_small(10).add(&BigUint::from(digit as u64)))
+        })
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.limbs.len() {
+            0 => Some(0),
+            1 => Some(self.limbs[0] as u64),
+            2 => Some(self.limbs[0] as u64 | (self.limbs[1] as u64) << 32),
+            _ => None,
+        }
+    }
+
+    fn bit_length(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn get_bit(&self, bit: usize) -> bool {
+        self.limbs
+            .get(bit / 32)
+            .is_some_and(|limb| limb >> (bit % 32) & 1 == 1)
+    }
+
+    pub fn add(&self, other: &BigUint) -> BigUint {
+        let mut limbs = Vec::with_capacity(self.limbs.len().max(other.limbs.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..self.limbs.len().max(other.limbs.len()) {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64
+                + *other.limbs.get(i).unwrap_or(&0) as u64
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint { limbs }.normalise()
+    }
+
+    // None if other is larger, as the result would be negative
+    pub fn checked_sub(&self, other: &BigUint) -> Option<BigUint> {
+        if *self < *other {
+            return None;
+        }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for i in 0..self.limbs.len() {
+            let mut difference =
+                self.limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += 1 << 32;
+                borrow = 1;
+            }
+            limbs.push(difference as u32);
+        }
+        Some(BigUint { limbs }.normalise())
+    }
+
+    fn mul_small(&self, factor: u32) -> BigUint {
+        self.mul(&BigUint::from(factor as u64))
+    }
+
+    pub fn mul(&self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint { limbs }.normalise()
+    }
+
+    // Binary long division, returning None when dividing by zero
+    pub fn checked_div_rem(&self, divisor: &BigUint) -> Option<(BigUint, BigUint)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let mut quotient = vec![0u32; self.limbs.len()];
+        let mut remainder = BigUint::zero();
+        for bit in (0..self.bit_length()).rev() {
+            remainder = remainder.add(&remainder);
+            if self.get_bit(bit) {
+                remainder = remainder.add(&BigUint::from(1));
+            }
+            if remainder >= *divisor {
+                remainder = remainder.checked_sub(divisor).unwrap();
+                quotient[bit / 32] |= 1 << (bit % 32);
+            }
+        }
+        Some((BigUint { limbs: quotient }.normalise(), remainder))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigUint {
+        let mut result = BigUint::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    // Returns None if the result could be longer than MAX_POW_BITS, which it cannot be if the exponent times
+    // the bit length of the base is within it. Powers of 0 and 1 never grow, so any exponent is allowed
+    pub fn checked_pow(&self, exponent: u32) -> Option<BigUint> {
+        let bits = match self.bit_length() {
+            0 | 1 => 0,
+            bits => bits as u64,
+        };
+        if exponent as u64 * bits > MAX_POW_BITS {
+            return None;
+        }
+        Some(self.pow(exponent))
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        BigUint {
+            limbs: vec![n as u32, (n >> 32) as u32],
+        }
+        .normalise()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Peel off nine decimal digits at a time, least significant first
+        let chunk_size = BigUint::from(1_000_000_000);
+        let mut chunks = vec![];
+        let mut remaining = self.clone();
+        while !remaining.is_zero() {
+            let (quotient, remainder) = remaining.checked_div_rem(&chunk_size).unwrap();
+            chunks.push(remainder.to_u64().unwrap());
+            remaining = quotient;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigUint {
+        BigUint::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for s in ["0", "7", "4294967296", "123456789012345678901234567890"] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(BigUint::parse(""), None);
+        assert_eq!(BigUint::parse("12a"), None);
+    }
+
+    #[test]
+    fn arithmetic_works() {
+        let a = big("340282366920938463463374607431768211456");
+        let b = big("18446744073709551616");
+        assert_eq!(
+            a.add(&b).to_string(),
+            "340282366920938463481821351505477763072"
+        );
+        assert_eq!(
+            a.checked_sub(&b).unwrap().to_string(),
+            "340282366920938463444927863358058659840"
+        );
+        assert_eq!(b.checked_sub(&a), None);
+        assert_eq!(b.mul(&b), a);
+        assert_eq!(a.checked_div_rem(&b), Some((b.clone(), BigUint::zero())));
+        assert_eq!(
+            big("100").checked_div_rem(&big("7")),
+            Some((big("14"), big("2")))
+        );
+        assert_eq!(a.checked_div_rem(&BigUint::zero()), None);
+        assert_eq!(big("2").pow(128), a);
+    }
+
+    #[test]
+    fn checked_pow_caps_result_size() {
+        assert_eq!(big("2").checked_pow(32768), Some(big("2").pow(32768)));
+        assert_eq!(big("2").checked_pow(32769), None);
+        assert_eq!(big("10").checked_pow(4000000000), None);
+        assert_eq!(big("1").checked_pow(u32::MAX), Some(big("1")));
+        assert_eq!(big("0").checked_pow(u32::MAX), Some(big("0")));
+        assert_eq!(big("0").checked_pow(0), Some(big("1")));
+    }
+
+    #[test]
+    fn comparison_works() {
+        assert!(big("18446744073709551616") > big("18446744073709551615"));
+        assert!(big("5") < big("6"));
+        assert_eq!(big("0"), BigUint::zero());
+        assert_eq!(BigUint::from(u64::MAX).to_u64(), Some(u64::MAX));
+        assert_eq!(big("18446744073709551616").to_u64(), None);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::get_problem_total;
+use crate::operators::{Operand, OperatorRegistry};
+use crate::worksheet::{pad_rows, parse_worksheet, LayoutError, Problem, ReadingDirection};
+use std::fmt::{Display, Write};
+
+// Columns of the worksheet shown either side of a problem, as real worksheets are thousands of columns wide
+const CONTEXT_COLUMNS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Html,
+}
+
+// One line per part, such as `Part 2: 4 + 431 + 623 = 1058`
+fn explain_part<T: Operand + Display>(
+    part: usize,
+    problem: &Problem,
+    registry: &OperatorRegistry,
+) -> String {
+    let subtotal = match get_problem_total::<T>(problem, registry) {
+        Some(subtotal) => subtotal.to_string(),
+        None => "overflows or is undefined".to_string(),
+    };
+    format!("Part {}: {} = {}", part, problem.expression, subtotal)
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Shows every problem with its columns picked out of the worksheet, and the operands and subtotal each
+// part reads from it. Fails if either part cannot read the worksheet
+pub fn explain_worksheet<T: Operand + Display>(
+    contents: &str,
+    registry: &OperatorRegistry,
+    format: Format,
+) -> Result<String, LayoutError> {
+    let rows = pad_rows(contents);
+    let width = rows.first().map_or(0, |row| row.len());
+    let problems = parse_worksheet(contents, ReadingDirection::PART_1)?
+        .into_iter()
+        .zip(parse_worksheet(contents, ReadingDirection::PART_2)?);
+
+    let mut output = match format {
+        Format::Text => String::new(),
+        Format::Html => "<!DOCTYPE html>\n<html>\n<body>\n".to_string(),
+    };
+    for (part_1, part_2) in problems {
+        let span = part_1.span.clone();
+        let heading = format!(
+            "Problem {} (columns {} to {})",
+            part_1.number,
+            span.start + 1,
+            span.end
+        );
+        let window =
+            span.start.saturating_sub(CONTEXT_COLUMNS)..(span.end + CONTEXT_COLUMNS).min(width);
+        let parts = [
+            explain_part::<T>(1, &part_1, registry),
+            explain_part::<T>(2, &part_2, registry),
+        ];
+        match format {
+            Format::Text => {
+                writeln!(output, "{}", heading).unwrap();
+                for row in &rows {
+                    let cells: String = row[window.clone()].iter().collect();
+                    writeln!(output, "{}", cells.trim_end()).unwrap();
+                }
+                // Underlines the problem's columns
+                let offset = span.start - window.start;
+                writeln!(output, "{}{}", " ".repeat(offset), "^".repeat(span.len())).unwrap();
+                for part in parts {
+                    writeln!(output, "{}", part).unwrap();
+                }
+                writeln!(output).unwrap();
+            }
+            Format::Html => {
+                writeln!(output, "<section>\n<h2>{}</h2>\n<pre>", heading).unwrap();
+                for row in &rows {
+                    let [before, inside, after] = [
+                        &row[window.start..span.start],
+                        &row[span.clone()],
+                        &row[span.end..window.end],
+                    ]
+                    .map(|cells| escape_html(&cells.iter().collect::<String>()));
+                    writeln!(
+                        output,
+                        "{}<mark>{}</mark>{}",
+                        before,
+                        inside,
+                        after.trim_end()
+                    )
+                    .unwrap();
+                }
+                writeln!(output, "</pre>").unwrap();
+                for part in parts {
+                    writeln!(output, "<p>{}</p>", escape_html(&part)).unwrap();
+                }
+                writeln!(output, "</section>").unwrap();
+            }
+        }
+    }
+    if format == Format::Html {
+        output.push_str("</body>\n</html>\n");
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bignum::BigUint;
+
+    const EXAMPLE: &str = "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +   *   +  ";
+
+    #[test]
+    fn explain_worksheet_as_text_works() {
+        let text =
+            explain_worksheet::<u64>(EXAMPLE, &OperatorRegistry::default(), Format::Text).unwrap();
+        let expected = "Problem 4 (columns 13 to 15)
+328  51 64
+64  387 23
+98  215 314
++   *   +
+        ^^^
+Part 1: 64 + 23 + 314 = 401
+Part 2: 623 + 431 + 4 = 1058
+";
+        assert!(text.starts_with("Problem 1 (columns 1 to 3)\n"));
+        assert!(text.contains("Part 2: 1 * 24 * 356 = 8544\n"));
+        assert!(text.ends_with(&format!("{}\n", expected)));
+        assert_eq!(text.matches("Problem").count(), 4);
+    }
+
+    #[test]
+    fn explain_worksheet_as_html_works() {
+        let input = "5 64\n3 23\n< +";
+        let mut registry = OperatorRegistry::default();
+        registry.register("<", None, |a, b| Some(a.min(b)), None);
+        let html = explain_worksheet::<u64>(input, &registry, Format::Html).unwrap();
+        assert!(html.starts_with(
+            "<!DOCTYPE html>\n<html>\n<body>\n<section>\n<h2>Problem 1 (columns 1 to 1)</h2>"
+        ));
+        assert!(html
+            .contains("<pre>\n<mark>5</mark> 64\n<mark>3</mark> 23\n<mark>&lt;</mark> +\n</pre>"));
+        assert!(html.contains("<p>Part 1: 5 &lt; 3 = 3</p>"));
+        assert!(html.contains("<pre>\n5 <mark>64</mark>\n"));
+        assert!(html.contains("<p>Part 2: 62 + 43 = 105</p>"));
+        assert!(html.ends_with("</section>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn explain_worksheet_shows_overflow() {
+        let input = "4294967296 9\n4294967296 9\n*          *";
+        let registry = OperatorRegistry::default();
+        let text = explain_worksheet::<u64>(input, &registry, Format::Text).unwrap();
+        assert!(text.contains("Part 1: 4294967296 * 4294967296 = overflows or is undefined\n"));
+        assert!(text.contains("Part 2: 99 = 99\n"));
+        let text = explain_worksheet::<BigUint>(input, &registry, Format::Text).unwrap();
+        assert!(text.contains("Part 1: 4294967296 * 4294967296 = 18446744073709551616\n"));
+    }
+
+    #[test]
+    fn explain_worksheet_reports_unreadable_layout() {
+        let input = "18446744073709551615 1\n12 34\n+ *";
+        let registry = OperatorRegistry::default();
+        assert_eq!(
+            explain_worksheet::<u64>(input, &registry, Format::Text),
+            Err(LayoutError { span: 21..22 })
+        );
+    }
+}
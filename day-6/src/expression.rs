@@ -0,0 +1,280 @@
+use crate::operators::{Operand, OperatorRegistry};
+use crate::worksheet::{Order, Reading, ReadingDirection};
+use std::fmt;
+use std::mem;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    // The digits of one operand, as read from the worksheet
+    Number(String),
+    Apply {
+        operator: String,
+        operands: Vec<Expression>,
+    },
+}
+
+impl Expression {
+    pub fn apply(operator: &str, operands: Vec<Expression>) -> Self {
+        Expression::Apply {
+            operator: operator.to_string(),
+            operands,
+        }
+    }
+
+    // The outermost operator, which is the last one applied
+    pub fn operator(&self) -> Option<&str> {
+        match self {
+            Expression::Number(_) => None,
+            Expression::Apply { operator, .. } => Some(operator),
+        }
+    }
+
+    // Returns None if any part of the expression overflows or is undefined
+    pub fn evaluate<T: Operand>(&self, registry: &OperatorRegistry) -> Option<T> {
+        match self {
+            Expression::Number(digits) => Some(
+                T::parse(digits)
+                    .unwrap_or_else(|| panic!("Failed to parse number from string: {}", digits)),
+            ),
+            Expression::Apply { operator, operands } => {
+                let values = operands
+                    .iter()
+                    .map(|operand| operand.evaluate(registry))
+                    .collect::<Option<Vec<T>>>()?;
+                registry.apply(operator, &values)
+            }
+        }
+    }
+}
+
+// Infix notation, with nested applications in brackets, such as `(12 + 34) * 5`
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Number(digits) => write!(f, "{}", digits),
+            Expression::Apply { operator, operands } => {
+                if operands.is_empty() {
+                    return write!(f, "{}", operator);
+                }
+                for (i, operand) in operands.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {} ", operator)?;
+                    }
+                    match operand {
+                        Expression::Number(_) => write!(f, "{}", operand)?,
+                        Expression::Apply { .. } => write!(f, "({})", operand)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Row {
+    Blank,
+    Numbers,
+    Operator(String),
+}
+
+// Cells that are blank only within this problem count as numbers, so that a problem with fewer operands
+// than its neighbours is read as before
+fn classify_row(cells: Option<&[char]>) -> Row {
+    let Some(cells) = cells else {
+        return Row::Blank;
+    };
+    let text: String = cells.iter().collect();
+    let text = text.trim();
+    if text
+        .chars()
+        .all(|c| c.is_ascii_digit() || c.is_whitespace())
+    {
+        Row::Numbers
+    } else {
+        Row::Operator(text.to_string())
+    }
+}
+
+// Reads a run of number rows, skipping rows or columns that hold no digits
+fn read_operands(block: &[&[char]], direction: ReadingDirection) -> Vec<String> {
+    let width = block.first().map_or(0, |row| row.len());
+    let read_column = |col: usize| -> String {
+        let cells = block.iter().map(|row| row[col]);
+        match direction.reading {
+            Reading::ColumnsBottomUp => cells.rev().filter(|c| !c.is_whitespace()).collect(),
+            _ => cells.filter(|c| !c.is_whitespace()).collect(),
+        }
+    };
+    let operands: Vec<String> = match direction.reading {
+        Reading::Rows => block
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim().to_string())
+            .collect(),
+        Reading::ColumnsTopDown | Reading::ColumnsBottomUp => match direction.order {
+            Order::LeftToRight => (0..width).map(read_column).collect(),
+            Order::RightToLeft => (0..width).rev().map(read_column).collect(),
+        },
+    };
+    operands
+        .into_iter()
+        .filter(|operand| !operand.is_empty())
+        .collect()
+}
+
+// Builds the expression for one problem's cells, read top to bottom, with None standing in for rows that
+// are blank across the whole worksheet. Number rows add operands to the current group, and an operator row
+// combines them into a single operand so operator rows can be stacked. A blank row closes the group, and
+// an operator row with no operands above it combines the closed groups, so groups nest as deeply as
+// needed. Returns None if any operands are left without an operator
+pub fn parse_expression(
+    rows: &[Option<&[char]>],
+    direction: ReadingDirection,
+) -> Option<Expression> {
+    let mut closed_groups = vec![];
+    let mut group: Vec<Expression> = vec![];
+    let mut number_rows: Vec<&[char]> = vec![];
+    // A trailing blank row closes the last group
+    for row in rows.iter().copied().chain([None]) {
+        let kind = classify_row(row);
+        if kind != Row::Numbers && !number_rows.is_empty() {
+            let operands = read_operands(&number_rows, direction);
+            group.extend(operands.into_iter().map(Expression::Number));
+            number_rows.clear();
+        }
+        match kind {
+            Row::Numbers => number_rows.extend(row),
+            Row::Operator(operator) => {
+                let operands = if group.is_empty() {
+                    mem::take(&mut closed_groups)
+                } else {
+                    mem::take(&mut group)
+                };
+                group = vec![Expression::apply(&operator, operands)];
+            }
+            Row::Blank => match group.as_slice() {
+                [] => {}
+                [Expression::Apply { .. }] => closed_groups.append(&mut group),
+                _ => return None,
+            },
+        }
+    }
+    match closed_groups.len() {
+        1 => closed_groups.pop(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bignum::BigUint;
+
+    fn number(digits: &str) -> Expression {
+        Expression::Number(digits.to_string())
+    }
+
+    // Treats empty rows as blank across the whole worksheet
+    fn parse(rows: &[&str], direction: ReadingDirection) -> Option<Expression> {
+        let rows: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        let rows: Vec<Option<&[char]>> = rows
+            .iter()
+            .map(|row| Some(row.as_slice()).filter(|row| !row.is_empty()))
+            .collect();
+        parse_expression(&rows, direction)
+    }
+
+    #[test]
+    fn parse_flat_expression_works() {
+        let rows = ["123", " 45", "  6", "*  "];
+        assert_eq!(
+            parse(&rows, ReadingDirection::PART_1),
+            Some(Expression::apply(
+                "*",
+                vec![number("123"), number("45"), number("6")]
+            ))
+        );
+        assert_eq!(
+            parse(&rows, ReadingDirection::PART_2),
+            Some(Expression::apply(
+                "*",
+                vec![number("1"), number("24"), number("356")]
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_nested_groups_works() {
+        let rows = ["12", "34", "+ ", "", " 5", " 6", "* ", "", "- ", " 2", "* "];
+        let expression = parse(&rows, ReadingDirection::PART_1).unwrap();
+        assert_eq!(
+            expression,
+            Expression::apply(
+                "*",
+                vec![
+                    Expression::apply(
+                        "-",
+                        vec![
+                            Expression::apply("+", vec![number("12"), number("34")]),
+                            Expression::apply("*", vec![number("5"), number("6")]),
+                        ]
+                    ),
+                    number("2"),
+                ]
+            )
+        );
+        assert_eq!(expression.to_string(), "((12 + 34) - (5 * 6)) * 2");
+        assert_eq!(expression.operator(), Some("*"));
+        let registry = OperatorRegistry::default();
+        assert_eq!(expression.evaluate::<u64>(&registry), Some(32));
+        // Each group of number rows is read in the given direction
+        let expression = parse(&rows, ReadingDirection::PART_2).unwrap();
+        assert_eq!(expression.to_string(), "((13 + 24) - (56)) * 2");
+        assert_eq!(expression.evaluate::<u64>(&registry), None);
+        assert_eq!(
+            expression.evaluate::<BigUint>(&registry),
+            None,
+            "37 - 56 is negative in both modes"
+        );
+    }
+
+    #[test]
+    fn groups_can_nest_deeply() {
+        // The blank row after the second group closes it, and the `+` below combines both groups
+        let rows = ["1", "2", "+", "", "3", "*", "", "+", "4", "||"];
+        let expression = parse(&rows, ReadingDirection::PART_1).unwrap();
+        assert_eq!(expression.to_string(), "((1 + 2) + (3)) || 4");
+        assert_eq!(
+            expression.evaluate::<u64>(&OperatorRegistry::default()),
+            Some(64)
+        );
+    }
+
+    #[test]
+    fn cells_blank_within_the_problem_are_skipped() {
+        let rows = ["12", "  ", "34", "+ ", "  "];
+        let expression = parse(&rows, ReadingDirection::PART_1).unwrap();
+        assert_eq!(expression.to_string(), "12 + 34");
+    }
+
+    #[test]
+    fn operands_without_an_operator_are_rejected() {
+        assert_eq!(parse(&["1", "2"], ReadingDirection::PART_1), None);
+        assert_eq!(parse(&["1", "+", "", "2"], ReadingDirection::PART_1), None);
+        assert_eq!(
+            parse(&["1", "+", "", "2", "+"], ReadingDirection::PART_1),
+            None
+        );
+        assert_eq!(parse(&["1", "2", "", "+"], ReadingDirection::PART_1), None);
+    }
+
+    #[test]
+    fn display_works_for_edge_cases() {
+        assert_eq!(number("7").to_string(), "7");
+        assert_eq!(Expression::apply("+", vec![]).to_string(), "+");
+        assert_eq!(
+            Expression::apply("min", vec![number("5"), number("3")]).to_string(),
+            "5 min 3"
+        );
+    }
+}
@@ -1,179 +1,155 @@
-use std::collections::HashMap;
+mod bignum;
+mod explain;
+mod expression;
+mod operators;
+mod worksheet;
+
+use bignum::BigUint;
+use explain::{explain_worksheet, Format};
+use operators::{Operand, OperatorRegistry};
+use std::env;
+use std::fmt;
 use std::fs;
-use std::string::ToString;
+use worksheet::{parse_worksheet, LayoutError, Order, Problem, Reading, ReadingDirection};
 
-fn get_trimmed_column(line: &str) -> Vec<String> {
-    line.split_whitespace()
-        .map(|s| s.trim().to_string())
-        .collect()
+// A problem whose result overflowed or was undefined, numbered from 1 as it reads on the worksheet
+#[derive(Debug, PartialEq)]
+struct ProblemError {
+    problem: usize,
+    operator: String,
 }
 
-fn rotate_2d_array<T: Clone>(array: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let mut rotated: Vec<Vec<T>> = Vec::new();
-    let num_columns = array[0].len();
-    let num_rows = array.len();
-
-    for col_idx in 0..num_columns {
-        let mut new_row: Vec<T> = Vec::new();
-        for row_idx in 0..num_rows {
-            new_row.push(array[row_idx][col_idx].clone());
-        }
-        rotated.push(new_row);
+impl fmt::Display for ProblemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Problem {} ({}) overflows or has an undefined result",
+            self.problem, self.operator
+        )
     }
-    rotated
 }
 
-fn parse_row(row: Vec<String>) -> Vec<u64> {
-    row.into_iter()
-        .map(|s| {
-            match s.parse::<u64>() {
-                Ok(num) => Some(num),
-                Err(_) => None, // Using 0 to represent non-numeric entries
-            }
-        })
-        .filter_map(|x| x)
-        .collect()
+#[derive(Debug, PartialEq)]
+enum EvaluationError {
+    // The worksheet cannot be read in the chosen direction
+    Layout(LayoutError),
+    Problem(ProblemError),
+    // Every problem has a value, but the running total overflowed when this one was added to it
+    Total { problem: usize },
 }
 
-fn get_column_total(row: Vec<String>) -> u64 {
-    let last_element = row.last().unwrap();
-    let parsed_row = parse_row(row.clone());
-    match last_element.as_str() {
-        "*" => {
-            parsed_row.iter().fold(1, |acc, element| acc * element)
-        }
-        "+" => {
-            parsed_row.iter().sum()
-        }
-        _ => {
-            panic!("Unexpected operator: {}", last_element);
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvaluationError::Layout(error) => error.fmt(f),
+            EvaluationError::Problem(error) => error.fmt(f),
+            EvaluationError::Total { problem } => {
+                write!(f, "Total overflows when adding problem {}", problem)
+            }
         }
     }
 }
 
-fn part_1(contents: &String) -> u64 {
-    let column_arrays: Vec<Vec<String>> = contents
-        .lines()
-        .map(get_trimmed_column)
-        .collect();
-
-    rotate_2d_array(column_arrays).iter().fold(0, |outer_acc, row| {
-        let column_value = get_column_total(row.clone());
-        outer_acc + column_value
-    })
+// Returns None if the problem overflows or its result is undefined
+fn get_problem_total<T: Operand>(problem: &Problem, registry: &OperatorRegistry) -> Option<T> {
+    problem.expression.evaluate(registry)
 }
 
-// Creates numbers by taking the nth character from each value
-fn create_numbers_from_each_column(row: &Vec<String>) -> Vec<u64> {
-    let char_map = row.iter().fold([vec![], vec![], vec![], vec![]], |acc: [Vec<char>; 4], s| {
-        let chars: Vec<char> = s.chars().collect();
-        let mut new_acc = acc.clone();
-        for (i, c) in chars.iter().enumerate() {
-            if c.is_whitespace() || *c == '*' || *c == '+' {
-                continue;
-            }
-            new_acc[i].push(*c);
-        }
-        new_acc
-    });
-
-    // Convert the character vectors into numbers
-    char_map.iter().filter_map(|char_vec| {
-        let num_str: String = char_vec.iter().collect();
-        if num_str.is_empty() {
-            return None;
-        }
-        let parsed = num_str.parse::<u64>().expect(format!("Failed to parse number from string: {}", num_str).as_str());
-        Some(parsed)
-    }).collect()
+// Adds up every problem's value in reading order, naming the first problem that fails on its own or
+// takes the total past what T can hold
+fn evaluate<T: Operand>(
+    contents: &str,
+    direction: ReadingDirection,
+    registry: &OperatorRegistry,
+) -> Result<T, EvaluationError> {
+    parse_worksheet(contents, direction)
+        .map_err(EvaluationError::Layout)?
+        .iter()
+        .try_fold(T::zero(), |total, problem| {
+            let value = get_problem_total(problem, registry).ok_or_else(|| {
+                EvaluationError::Problem(ProblemError {
+                    problem: problem.number,
+                    operator: problem
+                        .expression
+                        .operator()
+                        .unwrap_or_default()
+                        .to_string(),
+                })
+            })?;
+            total.checked_add(&value).ok_or(EvaluationError::Total {
+                problem: problem.number,
+            })
+        })
 }
 
-fn get_column_total_part_2(column: Vec<String>) -> u64 {
-    let last_element = column.last().unwrap();
-    println!("Column: {:?}", column);
-    let numbers = create_numbers_from_each_column(&column);
-    println!("Numbers: {:?}", numbers);
-    match last_element.as_str().trim() {
-        "*" => {
-            numbers.iter().fold(1, |acc, element| acc * element)
-        }
-        "+" => {
-            numbers.iter().sum()
-        }
-        _ => {
-            panic!("Unexpected operator: {} in column {:?}", last_element, column);
-        }
-    }
+#[cfg(test)]
+fn part_1<T: Operand>(contents: &str, registry: &OperatorRegistry) -> Result<T, EvaluationError> {
+    evaluate(contents, ReadingDirection::PART_1, registry)
 }
 
-fn part_2(contents: &String) -> u64 {
-    let rows: Vec<&str> = contents.lines().collect();
-
-    let column_count = rows[0].len();
-    let row_count = rows.len() - 1;
-
-    let mut current_column_index = 0;
-    let mut last_empty_column_index: i32 = -1;
-    let mut total: u64 = 0;
-
-    // Iterate each row in lockstep - when we find an empty column, we stop and go back to collect digits
-    while current_column_index <= column_count {
-        let is_empty_column = current_column_index == column_count || (0..row_count).all(|row_index| {
-            let line = rows[row_index];
-            let slice = line.chars().nth(current_column_index).unwrap();
-            slice.is_whitespace()
-        });
-
-        if is_empty_column {
-            let numbers = ((last_empty_column_index + 1) as usize..current_column_index).map(|i| {
-                let column: String = rows.iter().map(|line| {
-                    line.chars().nth(i).unwrap()
-                }).filter(|c| !['*', '+', ' '].contains(c)).collect();
-                column.parse::<u64>().unwrap()
-            });
-
-            let operator = rows[row_count].chars().nth((last_empty_column_index + 1) as usize).unwrap();
-
-            let result = match operator {
-                '*' => {
-                    println!("Multiplying numbers: {:?}", numbers.clone().collect::<Vec<u64>>());
-                    numbers.fold(1, |acc, element| acc * element)
-                }
-                '+' => {
-                    println!("Adding numbers: {:?}", numbers.clone().collect::<Vec<u64>>());
-                    numbers.sum()
-                }
-                _ => {
-                    panic!("Unexpected operator: {}", operator);
-                }
-            };
-            total += result;
-
-            last_empty_column_index = current_column_index as i32;
-        }
-
-        current_column_index += 1;
-    }
-    total
+#[cfg(test)]
+fn part_2<T: Operand>(contents: &str, registry: &OperatorRegistry) -> Result<T, EvaluationError> {
+    evaluate(contents, ReadingDirection::PART_2, registry)
 }
 
-// Gets each columns value, including whitespace, but with the trailing whitespace removed
-fn slice_row(line: &str) -> Vec<String> {
-   line.chars().collect::<Vec<char>>()
-       .chunks(4)
-       .map(|chunk| {
-           [chunk[0], chunk[1], chunk[2]].iter().collect::<String>()
-       })
-       .collect::<Vec<String>>()
+// Picks a reading direction from "rows", "down" or "up", read right to left if "rtl" is also given
+fn parse_direction(options: &[String]) -> Option<ReadingDirection> {
+    let reading = options.iter().find_map(|option| match option.as_str() {
+        "rows" => Some(Reading::Rows),
+        "down" => Some(Reading::ColumnsTopDown),
+        "up" => Some(Reading::ColumnsBottomUp),
+        _ => None,
+    })?;
+    let order = if options.iter().any(|option| option == "rtl") {
+        Order::RightToLeft
+    } else {
+        Order::LeftToRight
+    };
+    Some(ReadingDirection { reading, order })
 }
 
 fn main() {
     match fs::read_to_string("./day-6/assets/input.txt") {
         Ok(contents) => {
-            let total = part_1(&contents);
-            println!("{}", total);
-            let total_part_2 = part_2(&contents);
-            println!("{}", total_part_2);
+            let registry = OperatorRegistry::default();
+            let options: Vec<String> = env::args().skip(1).collect();
+            let big = options.iter().any(|option| option == "big");
+            // Pass "explain" to show how each problem is read, adding "html" for an HTML page
+            if options.iter().any(|option| option == "explain") {
+                let format = if options.iter().any(|option| option == "html") {
+                    Format::Html
+                } else {
+                    Format::Text
+                };
+                let explanation = if big {
+                    explain_worksheet::<BigUint>(&contents, &registry, format)
+                } else {
+                    explain_worksheet::<u64>(&contents, &registry, format)
+                };
+                match explanation {
+                    Ok(explanation) => print!("{}", explanation),
+                    Err(e) => eprintln!("{}", e),
+                }
+                return;
+            }
+            // Without a reading direction, both parts are printed
+            let directions = match parse_direction(&options) {
+                Some(direction) => vec![direction],
+                None => vec![ReadingDirection::PART_1, ReadingDirection::PART_2],
+            };
+            for direction in directions {
+                // Pass "big" to evaluate with arbitrary-precision integers instead of u64
+                let result = if big {
+                    evaluate::<BigUint>(&contents, direction, &registry)
+                        .map(|total| total.to_string())
+                } else {
+                    evaluate::<u64>(&contents, direction, &registry).map(|total| total.to_string())
+                };
+                match result {
+                    Ok(total) => println!("{}", total),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
         }
         Err(e) => eprintln!("Error reading file: {}", e),
     }
@@ -183,48 +159,31 @@ fn main() {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_get_trimmed_column() {
-        let line = "  apple   banana   cherry  ";
-        let result = get_trimmed_column(line);
-        assert_eq!(result, vec!["apple", "banana", "cherry"]);
-    }
-
-    #[test]
-    fn test_rotate_2d_array() {
-        let array = vec![
-            vec!["a1".to_string(), "a2".to_string(), "a3".to_string()],
-            vec!["b1".to_string(), "b2".to_string(), "b3".to_string()],
-            vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
-        ];
-        let rotated = rotate_2d_array(array);
-        let expected = vec![
-            vec!["a1".to_string(), "b1".to_string(), "c1".to_string()],
-            vec!["a2".to_string(), "b2".to_string(), "c2".to_string()],
-            vec!["a3".to_string(), "b3".to_string(), "c3".to_string()],
-        ];
-        assert_eq!(rotated, expected);
+    fn problem(rows: &[&str], direction: ReadingDirection) -> Problem {
+        parse_worksheet(&rows.join("\n"), direction)
+            .unwrap()
+            .remove(0)
     }
 
     #[test]
-    fn test_parse_row() {
-        let row = vec!["123".to_string(), "45".to_string(), "6".to_string(), "*".to_string()];
-        let parsed = parse_row(row);
-        assert_eq!(parsed, vec![123, 45, 6]);
+    fn test_parse_operands() {
+        let rows = ["123", " 45", "  6", "*  "];
+        let parsed = problem(&rows, ReadingDirection::PART_1).expression;
+        assert_eq!(parsed.to_string(), "123 * 45 * 6");
     }
 
     #[test]
     fn test_get_row_total_add() {
-        let row_add = vec!["1".to_string(), "2".to_string(), "3".to_string(), "+".to_string()];
-        let total_add = get_column_total(row_add);
-        assert_eq!(total_add, 6);
+        let row_add = problem(&["1", "2", "3", "+"], ReadingDirection::PART_1);
+        let total_add = get_problem_total(&row_add, &OperatorRegistry::default());
+        assert_eq!(total_add, Some(6u64));
     }
 
     #[test]
     fn test_get_row_total_multiply() {
-        let row_multiply = vec!["123".to_string(), "45".to_string(), "6".to_string(), "*".to_string()];
-        let total_multiply = get_column_total(row_multiply);
-        assert_eq!(total_multiply, 33210);
+        let row_multiply = problem(&["123", "45", "6", "*"], ReadingDirection::PART_1);
+        let total_multiply = get_problem_total(&row_multiply, &OperatorRegistry::default());
+        assert_eq!(total_multiply, Some(33210u64));
     }
 
     #[test]
@@ -233,54 +192,212 @@ mod tests {
  45 64  387 23
   6 98  215 314
 *   +   *   +  ";
-        let result = part_1(&input.to_string());
-        assert_eq!(result, 4277556);
+        let result = part_1(input, &OperatorRegistry::default());
+        assert_eq!(result, Ok(4277556u64));
     }
 
     #[test]
-    fn test_slice_row_every_4() {
-        let line = "123 328  51 64 ";
-        let result = slice_row(line);
-        assert_eq!(result, vec!["123", "328"," 51", "64 "]);
+    fn test_parse_operands_from_each_column() {
+        let rows = ["64 ", "23 ", "314", "*  "];
+        let numbers = problem(&rows, ReadingDirection::PART_2).expression;
+        assert_eq!(numbers.to_string(), "623 * 431 * 4");
     }
 
     #[test]
-    fn slice_row_works_with_smaller_values() {
-        let line = "12 32 5  6  ";
-        let result = slice_row(line);
-        assert_eq!(result, vec!["12", "32"," 5 ", "6 "]);
+    fn test_get_column_total_part_2_add() {
+        let column_add = problem(&["64 ", "23 ", "314", "+  "], ReadingDirection::PART_2);
+        let total_add = get_problem_total(&column_add, &OperatorRegistry::default());
+        assert_eq!(total_add, Some(1058u64));
     }
 
     #[test]
-    fn test_create_numbers_from_each_column() {
-        let row = vec!["64 ".to_string(), "23 ".to_string(), "314".to_string(), "*  ".to_string()];
-        let numbers = create_numbers_from_each_column(&row);
-        assert_eq!(numbers, [623, 431, 4]);
+    fn test_get_column_total_part_2_multiply() {
+        let column_multiply = problem(&[" 51", "387", "215", "*  "], ReadingDirection::PART_2);
+        let total_multiply = get_problem_total(&column_multiply, &OperatorRegistry::default());
+        assert_eq!(total_multiply, Some(3253600u64));
     }
 
     #[test]
-    fn test_get_column_total_part_2_add() {
-        let column_add = vec!["64 ".to_string(), "23 ".to_string(), "314".to_string(), "+  ".to_string()];
-        let total_add = get_column_total_part_2(column_add);
-        assert_eq!(total_add, 1058);
+    fn test_part_2() {
+        let row_1 = "123 328  51 64 ".to_string();
+        let row_2 = " 45 64  387 23 ".to_string();
+        let row_3 = "  6 98  215 314".to_string();
+        let row_4 = "*   +   *   +  ".to_string();
+        let input = format!("{}\n{}\n{}\n{}", row_1, row_2, row_3, row_4);
+        let result = part_2(&input.to_string(), &OperatorRegistry::default());
+        assert_eq!(result, Ok(3263827u64));
     }
 
     #[test]
-    fn test_get_column_total_part_2_multiply() {
-        let column_multiply = vec![" 51".to_string(), "387".to_string(), "215".to_string(), "*  ".to_string()];
-        let total_multiply = get_column_total_part_2(column_multiply);
-        assert_eq!(total_multiply, 3253600);
+    fn test_get_column_total_with_other_operators() {
+        let registry = OperatorRegistry::default();
+        let column = |op: &str| problem(&["64", "23", "4", op], ReadingDirection::PART_1);
+        assert_eq!(get_problem_total(&column("-"), &registry), Some(37u64));
+        assert_eq!(get_problem_total(&column("/"), &registry), Some(0u64));
+        assert_eq!(get_problem_total(&column("%"), &registry), Some(2u64));
+        assert_eq!(get_problem_total(&column("min"), &registry), Some(4u64));
+        assert_eq!(get_problem_total(&column("max"), &registry), Some(64u64));
+        assert_eq!(get_problem_total(&column("||"), &registry), Some(64234u64));
     }
 
+    #[test]
+    fn test_parse_operands_from_wide_columns() {
+        let rows = ["12345", "6    ", "7   8", "+    "];
+        let numbers = problem(&rows, ReadingDirection::PART_2).expression;
+        assert_eq!(numbers.to_string(), "167 + 2 + 3 + 4 + 58");
+    }
 
     #[test]
-    fn test_part_2() {
-        let row_1 ="123 328  51 64 ".to_string();
-        let row_2 =" 45 64  387 23 ".to_string();
-        let row_3 ="  6 98  215 314".to_string();
-        let row_4 ="*   +   *   +  ".to_string();
+    fn parts_work_with_ragged_rows() {
+        // The example with trailing whitespace trimmed from every row
+        let input = "123 328  51 64\n 45 64  387 23\n  6 98  215 314\n*   +   *   +";
+        let registry = OperatorRegistry::default();
+        assert_eq!(part_1(input, &registry), Ok(4277556u64));
+        assert_eq!(part_2(input, &registry), Ok(3263827u64));
+    }
+
+    #[test]
+    fn parts_work_with_many_wide_operands() {
+        let input = "12345  1\n 6789 22\n   10  3\n    2  4\n+     * ";
+        let registry = OperatorRegistry::default();
+        assert_eq!(
+            part_1(input, &registry),
+            Ok(12345u64 + 6789 + 10 + 2 + 22 * 3 * 4)
+        );
+        assert_eq!(
+            part_2(input, &registry),
+            Ok(1u64 + 26 + 37 + 481 + 5902 + 2 * 1234)
+        );
+    }
+
+    #[test]
+    fn test_part_1_and_part_2_with_other_operators() {
+        let row_1 = "123 328  51 64 ".to_string();
+        let row_2 = " 45 64  387 23 ".to_string();
+        let row_3 = "  6 98  215 314".to_string();
+        let row_4 = "max -   min %  ".to_string();
         let input = format!("{}\n{}\n{}\n{}", row_1, row_2, row_3, row_4);
-        let result = part_2(&input.to_string());
-        assert_eq!(result, 3263827);
+        let registry = OperatorRegistry::default();
+        // 64 % 23 % 314 is 18
+        assert_eq!(
+            part_1(&input, &registry),
+            Ok(123u64 + (328 - 64 - 98) + 51 + 18)
+        );
+        // 623 % 431 % 4 is 0
+        assert_eq!(part_2(&input, &registry), Ok(356u64 + (369 - 248 - 8) + 32));
+    }
+
+    #[test]
+    fn evaluate_works_in_every_direction() {
+        // Concatenation depends on the order of the operands, so every direction gives a different total
+        let input = "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n||  ||  ||  || ";
+        let registry = OperatorRegistry::default();
+        let evaluate =
+            |reading, order| evaluate::<u64>(input, ReadingDirection { reading, order }, &registry);
+        assert_eq!(
+            evaluate(Reading::Rows, Order::LeftToRight),
+            Ok(123456 + 3286498 + 51387215 + 6423314)
+        );
+        assert_eq!(
+            evaluate(Reading::ColumnsTopDown, Order::LeftToRight),
+            Ok(124356 + 3692488 + 32581175 + 6234314)
+        );
+        assert_eq!(
+            evaluate(Reading::ColumnsTopDown, Order::RightToLeft),
+            Ok(356241 + 8248369 + 17558132 + 4431623)
+        );
+        assert_eq!(
+            evaluate(Reading::ColumnsBottomUp, Order::LeftToRight),
+            Ok(142653 + 9638428 + 23185571 + 3261344)
+        );
+    }
+
+    #[test]
+    fn parts_work_with_nested_groups() {
+        let input = "12 1\n34 2\n+  +\n\n 5 3\n*  *\n\n-  *";
+        let registry = OperatorRegistry::default();
+        // (12 + 34) - 5 and (1 + 2) * 3
+        assert_eq!(part_1(input, &registry), Ok(41u64 + 9));
+        // (13 + 24) - 5 and 12 * 3, as the second problem's first group is a single column
+        assert_eq!(part_2(input, &registry), Ok(32u64 + 36));
+    }
+
+    #[test]
+    fn overflowing_problem_is_named() {
+        let input = "1 4294967296
+2 4294967296
++ *";
+        let registry = OperatorRegistry::default();
+        let error = ProblemError {
+            problem: 2,
+            operator: "*".to_string(),
+        };
+        assert_eq!(
+            part_1::<u64>(input, &registry),
+            Err(EvaluationError::Problem(error))
+        );
+        assert_eq!(
+            part_1::<u64>(input, &registry).unwrap_err().to_string(),
+            "Problem 2 (*) overflows or has an undefined result"
+        );
+        assert_eq!(
+            part_1::<BigUint>(input, &registry),
+            Ok(BigUint::parse("18446744073709551619").unwrap())
+        );
+    }
+
+    #[test]
+    fn overflowing_total_is_named() {
+        let input = "18446744073709551615 1
++ +";
+        let registry = OperatorRegistry::default();
+        // Problem 2 is only 1, but adding it takes the total past u64::MAX
+        let error = part_1::<u64>(input, &registry).unwrap_err();
+        assert_eq!(error, EvaluationError::Total { problem: 2 });
+        assert_eq!(error.to_string(), "Total overflows when adding problem 2");
+        assert_eq!(
+            part_1::<BigUint>(input, &registry).unwrap().to_string(),
+            "18446744073709551616"
+        );
+    }
+
+    #[test]
+    fn part_2_names_overflowing_problem() {
+        let input = "9999 1
+9999 2
+9999 3
+9999 4
+9999  
+*    +";
+        let registry = OperatorRegistry::default();
+        assert_eq!(
+            part_2::<u64>(input, &registry),
+            Err(EvaluationError::Problem(ProblemError {
+                problem: 1,
+                operator: "*".to_string()
+            }))
+        );
+        assert_eq!(
+            part_2::<BigUint>(input, &registry).unwrap().to_string(),
+            (BigUint::parse("99999")
+                .unwrap()
+                .pow(4)
+                .add(&BigUint::from(1234)))
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn unreadable_layout_is_an_error() {
+        // The rows are not lined up by column, so only part 1 can read them
+        let input = "18446744073709551615 1\n12 34\n+ *";
+        let registry = OperatorRegistry::default();
+        assert_eq!(
+            part_1::<BigUint>(input, &registry).unwrap().to_string(),
+            "18446744073709551661"
+        );
+        let error = part_2::<BigUint>(input, &registry).unwrap_err();
+        assert_eq!(error, EvaluationError::Layout(LayoutError { span: 21..22 }));
+        assert_eq!(error.to_string(), "Missing operator for columns 21..22");
     }
 }
@@ -0,0 +1,270 @@
+use crate::bignum::BigUint;
+use std::collections::HashMap;
+
+// Returns None when the result overflows or is undefined, such as dividing by zero
+pub type Fold = fn(u64, u64) -> Option<u64>;
+pub type BigFold = fn(&BigUint, &BigUint) -> Option<BigUint>;
+
+#[derive(Clone, Copy)]
+pub struct Operator {
+    // Starting value of the fold, and the result of applying the operator to no operands
+    pub identity: Option<u64>,
+    pub fold: Fold,
+    pub big_fold: Option<BigFold>,
+}
+
+impl Operator {
+    // Folds the operands left to right, starting from the identity if the operator has one and from the
+    // first operand otherwise, so `-` with 10, 2 and 3 gives (10 - 2) - 3
+    fn fold_with<T: Clone>(
+        &self,
+        operands: &[T],
+        identity: Option<T>,
+        fold: impl Fn(T, &T) -> Option<T>,
+    ) -> Option<T> {
+        let (initial, rest) = match (identity, operands.split_first()) {
+            (Some(identity), _) => (identity, operands),
+            (None, Some((first, rest))) => (first.clone(), rest),
+            (None, None) => panic!("Operator without an identity needs at least one operand"),
+        };
+        rest.iter().try_fold(initial, fold)
+    }
+}
+
+// Numbers a worksheet can be evaluated in
+pub trait Operand: Clone + std::fmt::Debug {
+    fn parse(s: &str) -> Option<Self>;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn zero() -> Self;
+    fn apply(operator: &Operator, operands: &[Self]) -> Option<Self>;
+}
+
+impl Operand for u64 {
+    fn parse(s: &str) -> Option<Self> {
+        s.parse().ok()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
+
+    fn zero() -> Self {
+        0
+    }
+
+    fn apply(operator: &Operator, operands: &[Self]) -> Option<Self> {
+        operator.fold_with(operands, operator.identity, |acc, element| {
+            (operator.fold)(acc, *element)
+        })
+    }
+}
+
+impl Operand for BigUint {
+    fn parse(s: &str) -> Option<Self> {
+        BigUint::parse(s)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self.add(other))
+    }
+
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+
+    fn apply(operator: &Operator, operands: &[Self]) -> Option<Self> {
+        let big_fold = operator.big_fold?;
+        let identity = operator.identity.map(BigUint::from);
+        operator.fold_with(operands, identity, |acc, element| big_fold(&acc, element))
+    }
+}
+
+pub struct OperatorRegistry {
+    operators: HashMap<String, Operator>,
+}
+
+impl OperatorRegistry {
+    pub fn empty() -> Self {
+        OperatorRegistry {
+            operators: HashMap::new(),
+        }
+    }
+
+    // Operators registered without a big fold can only be evaluated in u64
+    pub fn register(
+        &mut self,
+        symbol: &str,
+        identity: Option<u64>,
+        fold: Fold,
+        big_fold: Option<BigFold>,
+    ) {
+        self.operators.insert(
+            symbol.to_string(),
+            Operator {
+                identity,
+                fold,
+                big_fold,
+            },
+        );
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Operator> {
+        self.operators.get(symbol)
+    }
+
+    pub fn apply<T: Operand>(&self, symbol: &str, operands: &[T]) -> Option<T> {
+        match self.get(symbol) {
+            Some(operator) => T::apply(operator, operands),
+            None => panic!("Unexpected operator: {}", symbol),
+        }
+    }
+}
+
+// Appends the digits of b to a, so 12 || 34 is 1234
+fn concatenate(a: u64, b: u64) -> Option<u64> {
+    let digits = b.checked_ilog10().unwrap_or(0) + 1;
+    a.checked_mul(10u64.checked_pow(digits)?)?.checked_add(b)
+}
+
+fn concatenate_big(a: &BigUint, b: &BigUint) -> Option<BigUint> {
+    BigUint::parse(&format!("{}{}", a, b))
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        let mut registry = OperatorRegistry::empty();
+        registry.register("+", Some(0), u64::checked_add, Some(|a, b| Some(a.add(b))));
+        registry.register("*", Some(1), u64::checked_mul, Some(|a, b| Some(a.mul(b))));
+        registry.register("-", None, u64::checked_sub, Some(BigUint::checked_sub));
+        registry.register(
+            "/",
+            None,
+            u64::checked_div,
+            Some(|a, b| Some(a.checked_div_rem(b)?.0)),
+        );
+        registry.register(
+            "%",
+            None,
+            u64::checked_rem,
+            Some(|a, b| Some(a.checked_div_rem(b)?.1)),
+        );
+        registry.register(
+            "^",
+            None,
+            |a, b| a.checked_pow(u32::try_from(b).ok()?),
+            Some(|a, b| a.checked_pow(u32::try_from(b.to_u64()?).ok()?)),
+        );
+        // Has no identity, as u64::MAX would cap the minimum of big integers
+        registry.register(
+            "min",
+            None,
+            |a, b| Some(a.min(b)),
+            Some(|a, b| Some(a.min(b).clone())),
+        );
+        registry.register(
+            "max",
+            Some(0),
+            |a, b| Some(a.max(b)),
+            Some(|a, b| Some(a.max(b).clone())),
+        );
+        registry.register("||", None, concatenate, Some(concatenate_big));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_operators_work() {
+        let registry = OperatorRegistry::default();
+        assert_eq!(registry.apply("+", &[1u64, 2, 3]), Some(6));
+        assert_eq!(registry.apply("*", &[2u64, 3, 4]), Some(24));
+        assert_eq!(registry.apply("-", &[10u64, 2, 3]), Some(5));
+        assert_eq!(registry.apply("/", &[100u64, 5, 2]), Some(10));
+        assert_eq!(registry.apply("%", &[100u64, 7]), Some(2));
+        assert_eq!(registry.apply("^", &[2u64, 3, 2]), Some(64));
+        assert_eq!(registry.apply("min", &[5u64, 3, 9]), Some(3));
+        assert_eq!(registry.apply("max", &[5u64, 3, 9]), Some(9));
+        assert_eq!(registry.apply("||", &[12u64, 0, 345]), Some(120345));
+    }
+
+    #[test]
+    fn identities_apply_to_no_operands() {
+        let registry = OperatorRegistry::default();
+        assert_eq!(registry.apply::<u64>("+", &[]), Some(0));
+        assert_eq!(registry.apply::<u64>("*", &[]), Some(1));
+        assert_eq!(registry.apply::<u64>("max", &[]), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "needs at least one operand")]
+    fn operator_without_identity_needs_operands() {
+        OperatorRegistry::default().apply::<u64>("-", &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected operator: ?")]
+    fn unknown_operator_panics() {
+        OperatorRegistry::default().apply("?", &[1u64]);
+    }
+
+    #[test]
+    fn user_registered_operator_works() {
+        let mut registry = OperatorRegistry::default();
+        fn gcd(mut a: u64, mut b: u64) -> Option<u64> {
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            Some(a)
+        }
+        registry.register("gcd", Some(0), gcd, None);
+        assert_eq!(registry.apply("gcd", &[12u64, 18, 30]), Some(6));
+        // Without a big fold the operator cannot be used in big integer mode
+        assert_eq!(registry.apply("gcd", &[BigUint::from(12)]), None);
+    }
+
+    #[test]
+    fn overflow_and_undefined_results_are_detected() {
+        let registry = OperatorRegistry::default();
+        assert_eq!(registry.apply("*", &[u64::MAX, 2]), None);
+        assert_eq!(registry.apply("+", &[u64::MAX, 1]), None);
+        assert_eq!(registry.apply("-", &[1u64, 2]), None);
+        assert_eq!(registry.apply("/", &[1u64, 0]), None);
+        assert_eq!(registry.apply("^", &[10u64, 20]), None);
+        assert_eq!(registry.apply("||", &[u64::MAX, 1]), None);
+    }
+
+    #[test]
+    fn big_operators_work() {
+        let registry = OperatorRegistry::default();
+        let big = |n: u64| BigUint::from(n);
+        let product = registry.apply("*", &[big(u64::MAX), big(u64::MAX), big(2)]);
+        assert_eq!(
+            product.unwrap().to_string(),
+            "680564733841876926852962238568698216450"
+        );
+        assert_eq!(
+            registry
+                .apply("^", &[big(10), big(20)])
+                .unwrap()
+                .to_string(),
+            "100000000000000000000"
+        );
+        // Too long to compute, so reported like an overflow
+        assert_eq!(registry.apply("^", &[big(10), big(4000000000)]), None);
+        assert_eq!(
+            registry.apply("||", &[big(12), big(0), big(345)]),
+            Some(big(120345))
+        );
+        assert_eq!(registry.apply("-", &[big(1), big(2)]), None);
+        assert_eq!(registry.apply("min", &[big(5), big(3)]), Some(big(3)));
+        let above_u64 = |n: u64| big(u64::MAX).add(&big(n));
+        assert_eq!(
+            registry.apply("min", &[above_u64(7), above_u64(2), above_u64(9)]),
+            Some(above_u64(2))
+        );
+        assert_eq!(registry.apply("%", &[big(100), big(7)]), Some(big(2)));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::expression::{parse_expression, Expression};
+use std::fmt;
+use std::ops::Range;
+
+// How the digits of each operand are read from a problem's cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reading {
+    // Each operand row is one number
+    Rows,
+    // Each column is one number, with its most significant digit at the top
+    ColumnsTopDown,
+    // Each column is one number, with its most significant digit at the bottom
+    ColumnsBottomUp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    LeftToRight,
+    // Reverses the problems, and the columns within each problem when reading by column
+    RightToLeft,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReadingDirection {
+    pub reading: Reading,
+    pub order: Order,
+}
+
+impl ReadingDirection {
+    pub const PART_1: ReadingDirection = ReadingDirection {
+        reading: Reading::Rows,
+        order: Order::LeftToRight,
+    };
+    pub const PART_2: ReadingDirection = ReadingDirection {
+        reading: Reading::ColumnsTopDown,
+        order: Order::LeftToRight,
+    };
+}
+
+// One problem on the worksheet, made up of the cells between two all-blank columns
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    // Position of the problem on the worksheet, numbered from 1 left to right whatever the reading order
+    pub number: usize,
+    // Operands are read in the worksheet's reading direction
+    pub expression: Expression,
+    // Character columns of the worksheet the problem occupies
+    pub span: Range<usize>,
+}
+
+// A problem whose cells have no operator when read in the chosen direction, as happens when reading a
+// worksheet whose rows are not lined up by column
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutError {
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Missing operator for columns {:?}", self.span)
+    }
+}
+
+// Splits the worksheet into rows of characters, padding ragged rows with spaces to the widest row and
+// dropping trailing blank lines
+pub fn pad_rows(contents: &str) -> Vec<Vec<char>> {
+    let mut rows: Vec<Vec<char>> = contents
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    while rows
+        .last()
+        .is_some_and(|row| row.iter().all(|c| c.is_whitespace()))
+    {
+        rows.pop();
+    }
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in rows.iter_mut() {
+        row.resize(width, ' ');
+    }
+    rows
+}
+
+// Maximal runs of columns that have a non-blank cell in at least one row
+fn get_problem_spans(rows: &[Vec<char>]) -> Vec<Range<usize>> {
+    let width = rows.first().map_or(0, |row| row.len());
+    let is_blank_column = |col: usize| rows.iter().all(|row| row[col].is_whitespace());
+    let mut spans = vec![];
+    let mut start = None;
+    for col in 0..=width {
+        match (start, col == width || is_blank_column(col)) {
+            (None, false) => start = Some(col),
+            (Some(first), true) => {
+                spans.push(first..col);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+fn get_trimmed_column(line: &str) -> Vec<String> {
+    line.split_whitespace()
+        .map(|s| s.trim().to_string())
+        .collect()
+}
+
+fn rotate_2d_array<T: Clone>(array: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    let mut rotated: Vec<Vec<T>> = Vec::new();
+    let num_columns = array[0].len();
+    let num_rows = array.len();
+
+    for col_idx in 0..num_columns {
+        let mut new_row: Vec<T> = Vec::with_capacity(num_rows);
+        for row in &array {
+            new_row.push(row[col_idx].clone());
+        }
+        rotated.push(new_row);
+    }
+    rotated
+}
+
+// Character columns of each whitespace-separated field of a row
+fn get_field_spans(row: &[char]) -> Vec<Range<usize>> {
+    let line: String = row.iter().collect();
+    let mut start = 0;
+    get_trimmed_column(&line)
+        .into_iter()
+        .map(|field| {
+            while row[start].is_whitespace() {
+                start += 1;
+            }
+            let span = start..start + field.chars().count();
+            start = span.end;
+            span
+        })
+        .collect()
+}
+
+// Columns each problem takes up in every row that is not blank across the whole worksheet. Reading by rows
+// also accepts worksheets whose fields are not lined up with the blank columns, as long as every such row
+// has the same number of fields, with the nth field of each row belonging to the nth problem
+fn get_problem_cells(rows: &[Vec<char>], reading: Reading) -> Vec<Vec<Range<usize>>> {
+    let spans = get_problem_spans(rows);
+    let filled_rows: Vec<&Vec<char>> = rows
+        .iter()
+        .filter(|row| !row.iter().all(|c| c.is_whitespace()))
+        .collect();
+    let field_spans: Vec<Vec<Range<usize>>> =
+        filled_rows.iter().map(|row| get_field_spans(row)).collect();
+    let is_aligned = field_spans.iter().all(|fields| {
+        fields.len() == spans.len()
+            && fields
+                .iter()
+                .zip(&spans)
+                .all(|(field, span)| span.start <= field.start && field.end <= span.end)
+    });
+    let field_count = field_spans.first().map_or(0, |fields| fields.len());
+    let has_same_field_counts = field_spans.iter().all(|fields| fields.len() == field_count);
+    if reading == Reading::Rows && !is_aligned && has_same_field_counts && field_count > 0 {
+        return rotate_2d_array(field_spans);
+    }
+    spans
+        .into_iter()
+        .map(|span| vec![span; filled_rows.len()])
+        .collect()
+}
+
+// Parses the cells of each problem into an expression, which for the usual layout of operand rows above a
+// final operator row applies that operator to every operand
+pub fn parse_worksheet(
+    contents: &str,
+    direction: ReadingDirection,
+) -> Result<Vec<Problem>, LayoutError> {
+    let rows = pad_rows(contents);
+    let mut problems: Vec<Problem> = get_problem_cells(&rows, direction.reading)
+        .into_iter()
+        .enumerate()
+        .map(|(i, row_spans)| {
+            // Takes in every column the problem has a cell in
+            let start = row_spans.iter().map(|span| span.start).min().unwrap_or(0);
+            let end = row_spans.iter().map(|span| span.end).max().unwrap_or(0);
+            let span = start..end;
+            let mut row_spans = row_spans.into_iter();
+            let cells: Vec<Option<&[char]>> = rows
+                .iter()
+                .map(|row| {
+                    if row.iter().all(|c| c.is_whitespace()) {
+                        None
+                    } else {
+                        Some(&row[row_spans.next().unwrap()])
+                    }
+                })
+                .collect();
+            let expression =
+                parse_expression(&cells, direction).ok_or(LayoutError { span: span.clone() })?;
+            Ok(Problem {
+                number: i + 1,
+                expression,
+                span,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    if direction.order == Order::RightToLeft {
+        problems.reverse();
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +   *   +  ";
+
+    // The operands of problems that apply a single operator to plain numbers
+    fn get_operands(problems: &[Problem]) -> Vec<Vec<&str>> {
+        problems
+            .iter()
+            .map(|problem| match &problem.expression {
+                Expression::Apply { operands, .. } => operands
+                    .iter()
+                    .map(|operand| match operand {
+                        Expression::Number(digits) => digits.as_str(),
+                        _ => panic!("Nested expression in problem {}", problem.number),
+                    })
+                    .collect(),
+                Expression::Number(_) => panic!("Problem {} has no operator", problem.number),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_trimmed_column() {
+        let line = "  apple   banana   cherry  ";
+        let result = get_trimmed_column(line);
+        assert_eq!(result, vec!["apple", "banana", "cherry"]);
+    }
+
+    #[test]
+    fn test_rotate_2d_array() {
+        let array = vec![
+            vec!["a1".to_string(), "a2".to_string(), "a3".to_string()],
+            vec!["b1".to_string(), "b2".to_string(), "b3".to_string()],
+            vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
+        ];
+        let rotated = rotate_2d_array(array);
+        let expected = vec![
+            vec!["a1".to_string(), "b1".to_string(), "c1".to_string()],
+            vec!["a2".to_string(), "b2".to_string(), "c2".to_string()],
+            vec!["a3".to_string(), "b3".to_string(), "c3".to_string()],
+        ];
+        assert_eq!(rotated, expected);
+    }
+
+    #[test]
+    fn unaligned_rows_are_split_on_whitespace() {
+        let input = "18446744073709551615 1\n12 34\n+ *";
+        let problems = parse_worksheet(input, ReadingDirection::PART_1).unwrap();
+        assert_eq!(
+            get_operands(&problems),
+            vec![vec!["18446744073709551615", "12"], vec!["1", "34"]]
+        );
+        assert_eq!(problems[0].expression.operator(), Some("+"));
+        assert_eq!(problems[0].span, 0..20);
+        assert_eq!(problems[1].span, 2..22);
+        // Reading by columns needs the rows lined up, so the problem without an operator is reported
+        assert_eq!(
+            parse_worksheet(input, ReadingDirection::PART_2),
+            Err(LayoutError { span: 21..22 })
+        );
+        // Aligned worksheets with a blank cell in a problem are still split on blank columns
+        let input = "12 3\n45  \n+  +";
+        assert_eq!(
+            get_operands(&parse_worksheet(input, ReadingDirection::PART_1).unwrap()),
+            vec![vec!["12", "45"], vec!["3"]]
+        );
+    }
+
+    #[test]
+    fn parse_worksheet_works() {
+        let problems = parse_worksheet(EXAMPLE, ReadingDirection::PART_1).unwrap();
+        assert_eq!(problems.len(), 4);
+        assert_eq!(
+            problems[0],
+            Problem {
+                number: 1,
+                expression: Expression::apply(
+                    "*",
+                    ["123", "45", "6"]
+                        .map(|digits| Expression::Number(digits.to_string()))
+                        .to_vec()
+                ),
+                span: 0..3,
+            }
+        );
+        assert_eq!(get_operands(&problems)[3], vec!["64", "23", "314"]);
+        assert_eq!(problems[3].span, 12..15);
+    }
+
+    #[test]
+    fn ragged_rows_are_padded() {
+        // The same worksheet with trailing whitespace trimmed and a trailing blank line
+        let input = "123 328  51 64\n 45 64  387 23\n  6 98  215 314\n*   +   *   +\n\n";
+        let problems = parse_worksheet(input, ReadingDirection::PART_2).unwrap();
+        assert_eq!(
+            problems,
+            parse_worksheet(EXAMPLE, ReadingDirection::PART_2).unwrap()
+        );
+        assert_eq!(get_operands(&problems)[3], vec!["623", "431", "4"]);
+        assert_eq!(problems[3].expression.operator(), Some("+"));
+    }
+
+    #[test]
+    fn any_operand_count_and_width_is_supported() {
+        let input = "123456 1\n     7 2\n    89 3\n     1 4\n     2 5\nmax    ||";
+        let problems = parse_worksheet(input, ReadingDirection::PART_1).unwrap();
+        assert_eq!(
+            get_operands(&problems),
+            vec![
+                vec!["123456", "7", "89", "1", "2"],
+                vec!["1", "2", "3", "4", "5"]
+            ]
+        );
+        assert_eq!(problems[0].expression.operator(), Some("max"));
+        assert_eq!(problems[1].span, 7..9);
+        assert_eq!(problems[1].expression.operator(), Some("||"));
+        let problems = parse_worksheet(input, ReadingDirection::PART_2).unwrap();
+        assert_eq!(
+            get_operands(&problems),
+            vec![vec!["1", "2", "3", "4", "58", "67912"], vec!["12345"]]
+        );
+    }
+
+    #[test]
+    fn reading_directions_work() {
+        let direction = |reading, order| ReadingDirection { reading, order };
+        let problems = parse_worksheet(
+            EXAMPLE,
+            direction(Reading::ColumnsBottomUp, Order::LeftToRight),
+        )
+        .unwrap();
+        assert_eq!(get_operands(&problems)[0], vec!["1", "42", "653"]);
+        let problems = parse_worksheet(
+            EXAMPLE,
+            direction(Reading::ColumnsTopDown, Order::RightToLeft),
+        )
+        .unwrap();
+        assert_eq!(
+            get_operands(&problems),
+            vec![
+                vec!["4", "431", "623"],
+                vec!["175", "581", "32"],
+                vec!["8", "248", "369"],
+                vec!["356", "24", "1"]
+            ]
+        );
+        assert_eq!(problems[0].number, 4);
+        assert_eq!(problems[0].expression.operator(), Some("+"));
+        // Reading by rows keeps the rows in order, only the problems are reversed
+        let problems =
+            parse_worksheet(EXAMPLE, direction(Reading::Rows, Order::RightToLeft)).unwrap();
+        assert_eq!(get_operands(&problems)[0], vec!["64", "23", "314"]);
+    }
+
+    #[test]
+    fn nested_groups_are_parsed_per_problem() {
+        // Blank rows delimit the groups of every problem at once
+        let input = "12 7\n34 8\n+  *\n\n 5 9\n*  -\n\n-  +";
+        let problems = parse_worksheet(input, ReadingDirection::PART_1).unwrap();
+        assert_eq!(problems[0].expression.to_string(), "(12 + 34) - (5)");
+        assert_eq!(problems[1].expression.to_string(), "(7 * 8) + (9)");
+    }
+
+    #[test]
+    fn empty_worksheet_has_no_problems() {
+        assert_eq!(parse_worksheet("", ReadingDirection::PART_1), Ok(vec![]));
+        assert_eq!(
+            parse_worksheet("\n  \n", ReadingDirection::PART_2),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn missing_operator_is_an_error() {
+        let error = parse_worksheet("12 3\n45 6\n   +", ReadingDirection::PART_1).unwrap_err();
+        assert_eq!(error, LayoutError { span: 0..2 });
+        assert_eq!(error.to_string(), "Missing operator for columns 0..2");
+    }
+}
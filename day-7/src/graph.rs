@@ -0,0 +1,296 @@
+use crate::{expect_timelines, get_element_index, get_rows, ELEMENTS};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Node {
+    pub row: usize,
+    pub col: usize,
+    pub symbol: u8,
+}
+
+// Where a beam leaving a node ends up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Node(usize),
+    Floor,
+}
+
+// Sources and the splitters their beams reach, with an edge for every beam from a node to the next node
+// it hits, or to the floor. Beams that are absorbed or leave the side of the manifold have no edge, and a
+// node reached by two beams from the same node has two edges from it. Nodes are ordered by row, so every
+// edge points to a later node
+#[derive(Debug, PartialEq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<(usize, Target)>,
+}
+
+fn is_node(cell: u8) -> bool {
+    let element = &ELEMENTS[get_element_index(cell)];
+    element.is_source || element.is_splitter
+}
+
+// Follows a beam entering the cell at row, col down through the elements that are not nodes, returning
+// every node or floor it reaches
+fn trace_beam(rows: &[&[u8]], row: usize, col: usize) -> Vec<(usize, usize)> {
+    if row == rows.len() {
+        return vec![(row, col)];
+    }
+    let Some(&cell) = rows[row].get(col) else {
+        return vec![];
+    };
+    if is_node(cell) {
+        return vec![(row, col)];
+    }
+    get_output_columns(rows[row], col)
+        .into_iter()
+        .flat_map(|output| trace_beam(rows, row + 1, output))
+        .collect()
+}
+
+// Columns a beam reaching the element at col leaves the row from, as in process_line
+fn get_output_columns(row: &[u8], col: usize) -> Vec<usize> {
+    ELEMENTS[get_element_index(row[col])]
+        .outputs
+        .iter()
+        .filter_map(|&offset| {
+            let output = col.checked_add_signed(offset)?;
+            (offset == 0 || row.get(output) == Some(&b'.')).then_some(output)
+        })
+        .collect()
+}
+
+pub fn build_graph(input: &str) -> Graph {
+    let rows: Vec<&[u8]> = get_rows(input).collect();
+    let mut node_indices: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut nodes = vec![];
+    let mut outgoing = vec![];
+    // Every source is a node, and each splitter becomes one when a beam first reaches it
+    let mut pending: Vec<(usize, usize)> = vec![];
+    for (row, line) in rows.iter().enumerate() {
+        for (col, &cell) in line.iter().enumerate() {
+            if ELEMENTS[get_element_index(cell)].is_source {
+                pending.push((row, col));
+            }
+        }
+    }
+    while let Some((row, col)) = pending.pop() {
+        if node_indices.contains_key(&(row, col)) {
+            continue;
+        }
+        node_indices.insert((row, col), nodes.len());
+        nodes.push(Node {
+            row,
+            col,
+            symbol: rows[row][col],
+        });
+        let targets: Vec<(usize, usize)> = get_output_columns(rows[row], col)
+            .into_iter()
+            .flat_map(|output| trace_beam(&rows, row + 1, output))
+            .collect();
+        for &(target_row, target_col) in &targets {
+            if target_row < rows.len() {
+                pending.push((target_row, target_col));
+            }
+        }
+        outgoing.push(targets);
+    }
+
+    // Renumbers the nodes by row so that edges always point forwards
+    let mut order: Vec<usize> = (0..nodes.len()).collect();
+    order.sort_by_key(|&i| (nodes[i].row, nodes[i].col));
+    let mut new_index = vec![0; nodes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new;
+    }
+    let mut edges = vec![];
+    for &old in &order {
+        for &(row, col) in &outgoing[old] {
+            let target = match node_indices.get(&(row, col)) {
+                Some(&node) => Target::Node(new_index[node]),
+                None => Target::Floor,
+            };
+            edges.push((new_index[old], target));
+        }
+    }
+    Graph {
+        nodes: order.iter().map(|&i| nodes[i]).collect(),
+        edges,
+    }
+}
+
+// Every splitter in the graph was reached by a beam, so each one is a split
+pub fn count_splits_in_graph(graph: &Graph) -> u64 {
+    graph
+        .nodes
+        .iter()
+        .filter(|node| ELEMENTS[get_element_index(node.symbol)].is_splitter)
+        .count() as u64
+}
+
+// Pushes the timelines reaching each node along its edges in node order, with one more starting at each
+// source, and returns the number reaching the floor
+pub fn count_timelines_in_graph(graph: &Graph) -> u128 {
+    let mut timelines: Vec<u128> = graph
+        .nodes
+        .iter()
+        .map(|node| ELEMENTS[get_element_index(node.symbol)].is_source as u128)
+        .collect();
+    let mut floor = 0u128;
+    for &(from, target) in &graph.edges {
+        let count = timelines[from];
+        let total = match target {
+            Target::Node(to) => &mut timelines[to],
+            Target::Floor => &mut floor,
+        };
+        *total = expect_timelines(total.checked_add(count));
+    }
+    floor
+}
+
+fn escape(symbol: u8) -> String {
+    match symbol {
+        b'\\' | b'"' => format!("\\{}", symbol as char),
+        _ => (symbol as char).to_string(),
+    }
+}
+
+pub fn export_dot(graph: &Graph) -> String {
+    let mut dot = "digraph manifold {\n".to_string();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        writeln!(
+            dot,
+            "    n{} [label=\"{} ({}, {})\"];",
+            i,
+            escape(node.symbol),
+            node.row,
+            node.col
+        )
+        .unwrap();
+    }
+    dot.push_str("    floor [shape=box];\n");
+    for &(from, target) in &graph.edges {
+        match target {
+            Target::Node(to) => writeln!(dot, "    n{} -> n{};", from, to).unwrap(),
+            Target::Floor => writeln!(dot, "    n{} -> floor;", from).unwrap(),
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn export_json(graph: &Graph) -> String {
+    let nodes: Vec<String> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            format!(
+                "{{\"id\":{},\"symbol\":\"{}\",\"row\":{},\"col\":{}}}",
+                i,
+                escape(node.symbol),
+                node.row,
+                node.col
+            )
+        })
+        .collect();
+    let edges: Vec<String> = graph
+        .edges
+        .iter()
+        .map(|&(from, target)| match target {
+            Target::Node(to) => format!("{{\"from\":{},\"to\":{}}}", from, to),
+            Target::Floor => format!("{{\"from\":{},\"to\":\"floor\"}}", from),
+        })
+        .collect();
+    format!(
+        "{{\"nodes\":[{}],\"edges\":[{}]}}",
+        nodes.join(","),
+        edges.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_graph_works() {
+        let graph = build_graph(".S...\n.^.S.\n.....\n...S.\n.....");
+        let node = |row, col, symbol| Node { row, col, symbol };
+        assert_eq!(
+            graph.nodes,
+            vec![
+                node(0, 1, b'S'),
+                node(1, 1, b'^'),
+                node(1, 3, b'S'),
+                node(3, 3, b'S')
+            ]
+        );
+        assert_eq!(
+            graph.edges,
+            vec![
+                (0, Target::Node(1)),
+                (1, Target::Floor),
+                (1, Target::Floor),
+                (2, Target::Node(3)),
+                (3, Target::Floor)
+            ]
+        );
+        assert_eq!(count_splits_in_graph(&graph), 1);
+        assert_eq!(count_timelines_in_graph(&graph), 4);
+    }
+
+    #[test]
+    fn unreached_splitters_are_not_nodes() {
+        let graph = build_graph("..S..\n..^..\n.^...\n#...^\n.....");
+        // The left beam is absorbed and the right one misses the splitter on its row
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(
+            graph.edges,
+            vec![
+                (0, Target::Node(1)),
+                (1, Target::Node(2)),
+                (1, Target::Floor),
+                (2, Target::Floor)
+            ]
+        );
+        assert_eq!(count_splits_in_graph(&graph), 2);
+        assert_eq!(count_timelines_in_graph(&graph), 2);
+    }
+
+    #[test]
+    fn graph_counts_match_simulation() {
+        let inputs = [
+            crate::tests::EXAMPLE,
+            ".S.S.\n.....\n./.\\.\n#....\n.....",
+            "..S..\n..<..\n.>...\n.....",
+            "..S..\n.^.^.\n..^..\n./.\\.\n.^.^.\n.....",
+        ];
+        for input in inputs {
+            let graph = build_graph(input);
+            let (activations, _) = crate::simulate(input);
+            assert_eq!(
+                count_splits_in_graph(&graph),
+                crate::get_split_count(&activations)
+            );
+            assert_eq!(count_timelines_in_graph(&graph), crate::part_2(input));
+        }
+    }
+
+    #[test]
+    fn exports_work() {
+        let graph = build_graph(".S.\n.\\.\n...");
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(
+            export_dot(&graph),
+            "digraph manifold {\n    n0 [label=\"S (0, 1)\"];\n    floor [shape=box];\n    n0 -> floor;\n}\n"
+        );
+        assert_eq!(
+            export_json(&graph),
+            "{\"nodes\":[{\"id\":0,\"symbol\":\"S\",\"row\":0,\"col\":1}],\"edges\":[{\"from\":0,\"to\":\"floor\"}]}"
+        );
+        let graph = build_graph(".S.\n.^.\n...");
+        assert!(export_dot(&graph).contains("n0 -> n1;\n    n1 -> floor;\n    n1 -> floor;\n"));
+    }
+}
@@ -1,122 +1,311 @@
-use std::collections::HashSet;
-use std::{fs, thread};
-
-// Continue beams, or split them if they encounter splitter
-fn process_line(previous_line: &str, current_line: &str) -> (u64, String) {
-    let line_length = previous_line.len();
-    let mut result_line = String::with_capacity(line_length);
-    // Use a set for storing the indices of used splits, as we don't want duplicates to be registered by cells either side
-    let mut split_indices = HashSet::new();
-    for i in 0..line_length {
-        let prev_char = previous_line.chars().nth(i);
-        let prev_left_adjacent_char = if i > 0 {
-            previous_line.chars().nth(i - 1)
-        } else {
-            None
-        };
-        let prev_right_adjacent_char = previous_line.chars().nth(i + 1);
-
-        let curr_char = current_line.chars().nth(i);
-        let curr_left_adjacent_char = if i > 0 {
-            current_line.chars().nth(i - 1)
-        } else {
-            None
-        };
-        let curr_right_adjacent_char = current_line.chars().nth(i + 1);
-
-        let new_char = match (
-            (prev_left_adjacent_char, prev_char, prev_right_adjacent_char),
-            (curr_left_adjacent_char, curr_char, curr_right_adjacent_char),
-        ) {
-            ((_, Some('|' | 'S'), _), (_, Some('.'), _)) => '|', // Continue beam
-            // Split from the left
-            ((Some('|'), _, _), (Some('^'), Some('.'), _)) => '|',
-            // Split from the right
-            ((_, _, Some('|')), (_, Some('.'), Some('^'))) => '|',
-            // Increment split counter when we encounter split character
-            ((_, Some('|'), _), (_, Some('^'), _)) => {
-                split_indices.insert(i);
-                '^'
+mod graph;
+mod sensitivity;
+
+use graph::{
+    build_graph, count_splits_in_graph, count_timelines_in_graph, export_dot, export_json,
+};
+use sensitivity::{analyse_splitters, format_change};
+use std::env;
+use std::fs;
+
+const WORD_BITS: usize = 64;
+
+// One bit per column, with column c stored in bit c % 64 of word c / 64
+type Beams = Vec<u64>;
+
+struct Element {
+    symbol: u8,
+    name: &'static str,
+    // Columns, relative to the element, that a beam reaching it leaves the row from. Beams sent sideways
+    // only carry on through empty cells
+    outputs: &'static [isize],
+    // Whether a beam reaching the element counts towards the split count
+    is_splitter: bool,
+    // Whether the element starts a beam of its own
+    is_source: bool,
+}
+
+// Index of the empty cell in ELEMENTS
+const EMPTY: usize = 0;
+
+const ELEMENTS: [Element; 8] = [
+    Element {
+        symbol: b'.',
+        name: "empty",
+        outputs: &[0],
+        is_splitter: false,
+        is_source: false,
+    },
+    Element {
+        symbol: b'S',
+        name: "source",
+        outputs: &[0],
+        is_splitter: false,
+        is_source: true,
+    },
+    Element {
+        symbol: b'^',
+        name: "splitter",
+        outputs: &[-1, 1],
+        is_splitter: true,
+        is_source: false,
+    },
+    Element {
+        symbol: b'<',
+        name: "left splitter",
+        outputs: &[-1, 0],
+        is_splitter: true,
+        is_source: false,
+    },
+    Element {
+        symbol: b'>',
+        name: "right splitter",
+        outputs: &[0, 1],
+        is_splitter: true,
+        is_source: false,
+    },
+    Element {
+        symbol: b'/',
+        name: "left deflector",
+        outputs: &[-1],
+        is_splitter: false,
+        is_source: false,
+    },
+    Element {
+        symbol: b'\\',
+        name: "right deflector",
+        outputs: &[1],
+        is_splitter: false,
+        is_source: false,
+    },
+    Element {
+        symbol: b'#',
+        name: "absorber",
+        outputs: &[],
+        is_splitter: false,
+        is_source: false,
+    },
+];
+
+// Number of beams that reached each kind of element, indexed like ELEMENTS
+type Activations = [u64; ELEMENTS.len()];
+
+fn get_element_index(cell: u8) -> usize {
+    ELEMENTS
+        .iter()
+        .position(|element| element.symbol == cell)
+        .unwrap_or_else(|| panic!("Unexpected manifold element: {}", cell as char))
+}
+
+// One mask per element, marking the columns of the row it sits in
+fn get_element_masks(row: &[u8]) -> Vec<Beams> {
+    let mut masks = vec![vec![0; row.len().div_ceil(WORD_BITS)]; ELEMENTS.len()];
+    for (i, &cell) in row.iter().enumerate() {
+        masks[get_element_index(cell)][i / WORD_BITS] |= 1 << (i % WORD_BITS);
+    }
+    masks
+}
+
+// Moves every beam one column to the right, carrying bits across words
+fn shift_right(words: &[u64]) -> Beams {
+    let mut carry = 0;
+    words
+        .iter()
+        .map(|word| {
+            let shifted = word << 1 | carry;
+            carry = word >> (WORD_BITS - 1);
+            shifted
+        })
+        .collect()
+}
+
+// Moves every beam one column to the left, carrying bits across words
+fn shift_left(words: &[u64]) -> Beams {
+    let mut carry = 0;
+    let mut shifted: Beams = words
+        .iter()
+        .rev()
+        .map(|word| {
+            let shifted = word >> 1 | carry << (WORD_BITS - 1);
+            carry = word & 1;
+            shifted
+        })
+        .collect();
+    shifted.reverse();
+    shifted
+}
+
+// Sends the beams reaching each element out of its outputs, a word of columns at a time, and starts new
+// beams at the sources. Beams from the row above that run past the end of this row are lost. Returns how
+// many beams reached each kind of element, and the beams leaving the row
+fn process_line(beams: &[u64], row: &[u8]) -> (Activations, Beams) {
+    let masks = get_element_masks(row);
+    let mut activations = [0; ELEMENTS.len()];
+    let mut next_beams = vec![0; masks[EMPTY].len()];
+    for (i, (element, mask)) in ELEMENTS.iter().zip(&masks).enumerate() {
+        let hit: Beams = mask.iter().zip(beams).map(|(a, b)| a & b).collect();
+        activations[i] = hit.iter().map(|word| word.count_ones() as u64).sum();
+        for &offset in element.outputs {
+            let outputs = match offset {
+                -1 => shift_left(&hit),
+                0 => hit.clone(),
+                1 => shift_right(&hit),
+                _ => panic!("Unsupported output offset: {}", offset),
+            };
+            for (j, word) in outputs.into_iter().enumerate() {
+                next_beams[j] |= match offset {
+                    0 => word,
+                    _ => word & masks[EMPTY][j],
+                };
             }
-            // Dont change splitters
-            ((_, _, _), (_, Some('^'), _)) => '^',
-            _ => '.', // No change
-        };
-        result_line.push(new_char);
+        }
+        if element.is_source {
+            next_beams.iter_mut().zip(mask).for_each(|(a, b)| *a |= b);
+        }
     }
-    (split_indices.len() as u64, result_line)
+    (activations, next_beams)
 }
 
-fn part_1(input: &str) -> (u64, Vec<String>) {
-    let result = input
-        .lines()
+fn get_split_count(activations: &Activations) -> u64 {
+    ELEMENTS
+        .iter()
+        .zip(activations)
+        .filter(|(element, _)| element.is_splitter)
+        .map(|(_, count)| count)
+        .sum()
+}
+
+fn render_line(row: &[u8], beams: &[u64]) -> String {
+    row.iter()
         .enumerate()
-        .fold((0, vec![]), |acc, (i, line)| {
-            if i == 0 {
-                return (0, vec![line.to_string()]);
-            }
-            let previous_line = acc.1.last().expect("No previous line");
-            let (split_count, line_output) = process_line(previous_line, line);
-            println!("Processing line");
-            println!("Previous {}", previous_line);
-            println!("Current  {}", line);
-            println!("Output   {} -> {}", line_output, split_count);
-            (acc.0 + split_count, [acc.1, vec![line_output]].concat())
-        });
-    result
-}
-
-// TODO: add up previous rows
-fn process_line_part_2(
-    previous_line: &str,
-    previous_line_with_splits: &str,
-    current_line: &str,
-) -> String {
-    current_line.chars().enumerate().fold(
-        String::with_capacity(current_line.len()),
-        |mut acc, (i, curr_char)| {
-            let previous_left_char = if i > 0 {
-                Some(previous_line.chars().nth(i - 1).unwrap())
-            } else {
-                None
-            };
-            let previous_right_char = if i <= previous_line.len() {
-                Some(previous_line.chars().nth(i + 1).unwrap())
-            } else {
-                None
-            };
-            let previous_char = Some(previous_line.chars().nth(i).unwrap());
-            let previous_char_with_splits = Some(previous_line_with_splits.chars().nth(i).unwrap());
-
-            match (
-                previous_char_with_splits,
-                (previous_left_char, previous_char, previous_right_char),
-            ) {
-                // Previously connected to a splliter, so add up left and right
-                ((Some('^'), (Some(a), _, Some(b)))) => {
-                    let left_digit = if a.is_digit(10) {
-                        a.to_digit(10).unwrap()
-                    } else {
-                        0
-                    };
-                    let right_digit = if b.is_digit(10) {
-                        b.to_digit(10).unwrap()
-                    } else {
-                        0
-                    };
-                    (left_digit + right_digit).to_string()
-                }
+        .map(|(i, &byte)| match byte {
+            b'.' if beams[i / WORD_BITS] >> (i % WORD_BITS) & 1 == 1 => '|',
+            _ => byte as char,
+        })
+        .collect()
+}
+
+// Rows of the manifold as bytes, without the carriage returns of Windows line endings, including one left
+// on a last line that has no line feed
+fn get_rows(input: &str) -> impl Iterator<Item = &[u8]> {
+    input
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).as_bytes())
+}
 
-                _ => curr_char.to_string(),
+// Runs the beams down every row, returning the activations of every kind of element and the diagram
+fn simulate(input: &str) -> (Activations, Vec<String>) {
+    let mut beams: Beams = vec![];
+    let mut activations = [0; ELEMENTS.len()];
+    let mut output = vec![];
+    for row in get_rows(input) {
+        let (row_activations, next_beams) = process_line(&beams, row);
+        for (total, count) in activations.iter_mut().zip(row_activations) {
+            *total += count;
+        }
+        output.push(render_line(row, &next_beams));
+        beams = next_beams;
+    }
+    (activations, output)
+}
+
+#[cfg(test)]
+fn part_1(input: &str) -> (u64, Vec<String>) {
+    let (activations, output) = simulate(input);
+    (get_split_count(&activations), output)
+}
+
+// Timeline counts can double at every splitter, so every sum or product of them is checked
+fn expect_timelines(count: Option<u128>) -> u128 {
+    count.expect("Timeline count does not fit in a u128")
+}
+
+// Every timeline reaching an element carries on from each of its outputs, so counts add up where beams
+// merge. Sideways outputs only land on empty cells, as in process_line
+fn process_line_part_2(timelines: &[u128], row: &[u8]) -> Vec<u128> {
+    let mut next_timelines = vec![0u128; row.len()];
+    let mut add_timelines = |col: usize, count: u128| {
+        next_timelines[col] = expect_timelines(next_timelines[col].checked_add(count));
+    };
+    for (i, &cell) in row.iter().enumerate() {
+        let element = &ELEMENTS[get_element_index(cell)];
+        if element.is_source {
+            add_timelines(i, 1);
+        }
+        let count = timelines.get(i).copied().unwrap_or(0);
+        if count == 0 {
+            continue;
+        }
+        for &offset in element.outputs {
+            let Some(col) = i.checked_add_signed(offset).filter(|&col| col < row.len()) else {
+                continue;
+            };
+            if offset == 0 || row[col] == b'.' {
+                add_timelines(col, count);
             }
-        },
+        }
+    }
+    next_timelines
+}
+
+fn part_2(input: &str) -> u128 {
+    let timelines = get_rows(input).fold(vec![], |timelines: Vec<u128>, row| {
+        process_line_part_2(&timelines, row)
+    });
+    expect_timelines(
+        timelines
+            .iter()
+            .try_fold(0u128, |total, &count| total.checked_add(count)),
     )
 }
 
 fn main() {
     match fs::read_to_string("./day-7/assets/input.txt") {
         Ok(contents) => {
-            println!("Split count {}", part_1(&*contents).0)
+            // Pass "dot" or "json" to print the splitter network instead, or "sensitivity" to show what
+            // removing each splitter would change
+            match env::args().nth(1).as_deref() {
+                Some("dot") => {
+                    print!("{}", export_dot(&build_graph(&contents)));
+                    return;
+                }
+                Some("json") => {
+                    println!("{}", export_json(&build_graph(&contents)));
+                    return;
+                }
+                Some("sensitivity") => {
+                    let (activations, _) = simulate(&contents);
+                    let split_count = get_split_count(&activations);
+                    let timeline_count = part_2(&contents);
+                    for sensitivity in analyse_splitters(&contents) {
+                        println!(
+                            "{} ({}, {}): split count {}, timeline count {}",
+                            sensitivity.symbol as char,
+                            sensitivity.row,
+                            sensitivity.col,
+                            format_change(split_count as u128, sensitivity.split_count as u128),
+                            format_change(timeline_count, sensitivity.timeline_count)
+                        );
+                    }
+                    return;
+                }
+                _ => {}
+            }
+            let (activations, _) = simulate(&contents);
+            println!("Split count {}", get_split_count(&activations));
+            println!("Timeline count {}", part_2(&contents));
+            let graph = build_graph(&contents);
+            println!(
+                "Graph of {} nodes and {} edges gives split count {} and timeline count {}",
+                graph.nodes.len(),
+                graph.edges.len(),
+                count_splits_in_graph(&graph),
+                count_timelines_in_graph(&graph)
+            );
+            // Skips the empty cells, which every beam passes through
+            for (element, count) in ELEMENTS.iter().zip(activations).skip(EMPTY + 1) {
+                println!("{} activations {}", element.name, count);
+            }
         }
         Err(e) => eprintln!("Error reading file: {}", e),
     }
@@ -126,31 +315,22 @@ fn main() {
 mod tests {
     use super::*;
 
-    #[test]
-    fn process_line_works() {
-        let previous_line = ".|.|||.||.||.|.";
-        let current_line = ".^.^.^.^.^...^.";
-        let expected = "|^|^|^|^|^|||^|";
-        assert_eq!(
-            process_line(previous_line, current_line),
-            (5u64, expected.to_string())
-        );
+    // Beams leaving a rendered row, which are drawn as `|` and start at `S`
+    fn get_beams(row: &[u8]) -> Beams {
+        let mut beams = vec![0; row.len().div_ceil(WORD_BITS)];
+        for (i, &cell) in row.iter().enumerate() {
+            if cell == b'|' || cell == b'S' {
+                beams[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+            }
+        }
+        beams
     }
 
-    #[test]
-    fn process_long_line_works() {
-        let previous_line = "......................................................................|......................................................................";
-        let current_line = "......................................................................^......................................................................";
-        let expected_line = ".....................................................................|^|.....................................................................";
-        assert_eq!(
-            process_line(previous_line, current_line),
-            (1u64, expected_line.to_string())
-        );
+    fn get_activation(activations: &Activations, symbol: u8) -> u64 {
+        activations[get_element_index(symbol)]
     }
 
-    #[test]
-    fn part_1_works() {
-        let input = ".......S.......
+    pub(crate) const EXAMPLE: &str = ".......S.......
 ...............
 .......^.......
 ...............
@@ -166,6 +346,56 @@ mod tests {
 ...............
 .^.^.^.^.^...^.
 ...............";
+
+    #[test]
+    fn process_line_works() {
+        let previous_line = ".|.|||.||.||.|.";
+        let current_line = ".^.^.^.^.^...^.";
+        let expected = "|^|^|^|^|^|||^|";
+        let (activations, beams) = process_line(
+            &get_beams(previous_line.as_bytes()),
+            current_line.as_bytes(),
+        );
+        assert_eq!(get_split_count(&activations), 5);
+        assert_eq!(render_line(current_line.as_bytes(), &beams), expected);
+    }
+
+    #[test]
+    fn process_long_line_works() {
+        let previous_line = "......................................................................|......................................................................";
+        let current_line = "......................................................................^......................................................................";
+        let expected_line = ".....................................................................|^|.....................................................................";
+        let (activations, beams) = process_line(
+            &get_beams(previous_line.as_bytes()),
+            current_line.as_bytes(),
+        );
+        assert_eq!(get_split_count(&activations), 1);
+        assert_eq!(render_line(current_line.as_bytes(), &beams), expected_line);
+    }
+
+    #[test]
+    fn process_line_splits_across_words() {
+        let mut previous_line = vec![b'.'; 130];
+        previous_line[63] = b'|';
+        previous_line[64] = b'|';
+        let mut current_line = vec![b'.'; 130];
+        current_line[63] = b'^';
+        current_line[64] = b'^';
+        let (activations, beams) = process_line(&get_beams(&previous_line), &current_line);
+        assert_eq!(get_split_count(&activations), 2);
+        let mut expected = vec![b'.'; 130];
+        expected[62] = b'|';
+        expected[63] = b'^';
+        expected[64] = b'^';
+        expected[65] = b'|';
+        assert_eq!(
+            render_line(&current_line, &beams),
+            String::from_utf8(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn part_1_works() {
         let expected = ".......S.......
 .......|.......
 ......|^|......
@@ -183,20 +413,144 @@ mod tests {
 |^|^|^|^|^|||^|
 |.|.|.|.|.|||.|";
         assert_eq!(
-            part_1(input),
+            part_1(EXAMPLE),
             (21, expected.lines().map(|s| s.to_string()).collect())
         );
     }
 
+    #[test]
+    fn deflectors_and_absorbers_work() {
+        let input = ".S.S.\n.....\n./.\\.\n#....\n.....";
+        let (activations, output) = simulate(input);
+        assert_eq!(output, vec![".S.S.", ".|.|.", "|/.\\|", "#...|", "....|"]);
+        assert_eq!(get_split_count(&activations), 0);
+        assert_eq!(get_activation(&activations, b'/'), 1);
+        assert_eq!(get_activation(&activations, b'\\'), 1);
+        assert_eq!(get_activation(&activations, b'#'), 1);
+        assert_eq!(part_2(input), 1);
+    }
+
+    #[test]
+    fn one_sided_splitters_merge_beams() {
+        let input = "..S..\n..<..\n.>...\n.....";
+        let (activations, output) = simulate(input);
+        assert_eq!(output, vec!["..S..", ".|<..", ".>|..", ".||.."]);
+        // The right splitter's beam merges with the one passing beside it, so two timelines share a beam
+        assert_eq!(get_split_count(&activations), 2);
+        assert_eq!(get_activation(&activations, b'<'), 1);
+        assert_eq!(get_activation(&activations, b'>'), 1);
+        assert_eq!(part_2(input), 3);
+    }
+
+    #[test]
+    fn multiple_sources_work() {
+        let input = ".S...\n.^.S.\n.....\n...S.\n.....";
+        let (activations, output) = simulate(input);
+        assert_eq!(output, vec![".S...", "|^|S.", "|.||.", "|.|S.", "|.||."]);
+        assert_eq!(get_split_count(&activations), 1);
+        // A beam passing through a source carries on alongside the source's own beam
+        assert_eq!(get_activation(&activations, b'S'), 1);
+        assert_eq!(part_2(input), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected manifold element: ?")]
+    fn unknown_element_panics() {
+        simulate("S\n?");
+    }
+
+    #[test]
+    fn windows_line_endings_are_ignored() {
+        let input = EXAMPLE.replace('\n', "\r\n");
+        for input in [
+            input.clone(),
+            format!("{}\r\n", input),
+            format!("{}\r", input),
+        ] {
+            let (activations, output) = simulate(&input);
+            assert_eq!(get_split_count(&activations), 21);
+            assert_eq!(output, simulate(EXAMPLE).1);
+            assert_eq!(part_2(&input), 40);
+            assert_eq!(graph::count_timelines_in_graph(&build_graph(&input)), 40);
+            assert_eq!(analyse_splitters(&input), analyse_splitters(EXAMPLE));
+        }
+    }
+
     #[test]
     fn process_line_part_2_works() {
-        let previous_line = "1.1.1.1.1.111.1";
-        let previous_line_with_splits = "|^|^|^|^|^|||^|";
-        let current_line = ".|.|||.||.||.|.";
-        let expected = ".2.212.21.11.2.";
-        assert_eq!(
-            process_line_part_2(previous_line, previous_line_with_splits, current_line),
-            expected.to_string()
+        let row = ".^.^.^.^.^...^.".as_bytes();
+        let timelines = [0, 1, 0, 2, 1, 1, 0, 1, 1, 0, 3, 1, 0, 1, 0];
+        // Splitters send their count either side, and counts add up where beams merge
+        let expected = [1, 0, 3, 0, 4, 0, 2, 0, 2, 0, 3, 1, 1, 0, 1];
+        assert_eq!(process_line_part_2(&timelines, row), expected);
+    }
+
+    #[test]
+    fn part_2_works() {
+        assert_eq!(part_2(EXAMPLE), 40);
+    }
+
+    // A triangle of splitters doubles the timelines with every row, as every row is fully split
+    fn get_splitter_triangle(levels: usize) -> String {
+        let width = 2 * levels + 3;
+        let centre = width / 2;
+        let mut rows = vec![];
+        for level in 0..=levels {
+            let mut row = vec![b'.'; width];
+            match level {
+                0 => row[centre] = b'S',
+                _ => (0..level).for_each(|i| row[centre + 1 - level + 2 * i] = b'^'),
+            }
+            rows.push(String::from_utf8(row).unwrap());
+        }
+        rows.push(".".repeat(width));
+        rows.join("\n")
+    }
+
+    #[test]
+    fn part_2_counts_past_single_digits() {
+        assert_eq!(part_2(&get_splitter_triangle(10)), 1 << 10);
+        assert_eq!(part_2(&get_splitter_triangle(127)), 1 << 127);
+    }
+
+    #[test]
+    #[should_panic(expected = "Timeline count does not fit in a u128")]
+    fn part_2_detects_overflowing_total() {
+        // Every column fits in a u128, but there are 2^128 timelines in all
+        part_2(&get_splitter_triangle(128));
+    }
+
+    // Run with `cargo test --release -p day-7 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_large_manifold() {
+        let size = 10_000;
+        let mut state = 42u64;
+        let mut input = String::with_capacity(size * (size + 1));
+        for row in 0..size {
+            for col in 0..size {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let cell = match (row, col) {
+                    (0, col) if col == size / 2 => 'S',
+                    // Splitters only sit on every other row, as in the puzzle input
+                    (row, _) if row % 2 == 0 && row > 0 && (state >> 33).is_multiple_of(10) => '^',
+                    _ => '.',
+                };
+                input.push(cell);
+            }
+            input.push('\n');
+        }
+        let start = std::time::Instant::now();
+        let (split_count, output) = part_1(&input);
+        println!(
+            "{}x{}: {} splits in {:?}",
+            size,
+            size,
+            split_count,
+            start.elapsed()
         );
+        assert_eq!(output.len(), size);
     }
 }
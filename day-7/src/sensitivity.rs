@@ -0,0 +1,209 @@
+use crate::{
+    expect_timelines, get_element_index, get_rows, get_split_count, process_line,
+    process_line_part_2, Beams, ELEMENTS,
+};
+
+// The counts for the manifold with one splitter replaced by an empty cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sensitivity {
+    pub row: usize,
+    pub col: usize,
+    pub symbol: u8,
+    pub split_count: u64,
+    pub timeline_count: u128,
+}
+
+fn add_timelines(a: u128, b: u128) -> u128 {
+    expect_timelines(a.checked_add(b))
+}
+
+fn multiply_timelines(a: u128, b: u128) -> u128 {
+    expect_timelines(a.checked_mul(b))
+}
+
+// Timelines reaching the floor for each one reaching the element at col, given how many reach the floor
+// for each one leaving the row from each column. Sideways outputs only land on empty cells, as in
+// process_line_part_2
+fn get_outgoing_weight(row: &[u8], col: usize, weights: &[u128]) -> u128 {
+    ELEMENTS[get_element_index(row[col])]
+        .outputs
+        .iter()
+        .filter_map(|&offset| {
+            let output = col
+                .checked_add_signed(offset)
+                .filter(|&output| output < row.len())?;
+            (offset == 0 || row[output] == b'.').then(|| weights.get(output).copied().unwrap_or(0))
+        })
+        .fold(0, add_timelines)
+}
+
+// Runs the beams and timelines down the rows once to find what reaches each row, and the timelines back up
+// once to find what each column leaving a row is worth at the floor. Removing a splitter leaves everything
+// above its row alone, so the split count only needs the rows below it re-run until the beams match the
+// original ones again. The timeline count is linear in the timelines reaching the row, so it only needs the
+// outputs of the splitter and its neighbours reweighed, as a neighbour's sideways output can now land on
+// the emptied cell
+pub fn analyse_splitters(input: &str) -> Vec<Sensitivity> {
+    let rows: Vec<&[u8]> = get_rows(input).collect();
+
+    // Beams and timelines reaching each row, and the splits in all the rows above it
+    let mut beams_in: Vec<Beams> = vec![vec![]];
+    let mut timelines_in: Vec<Vec<u128>> = vec![vec![]];
+    let mut splits_before = vec![0];
+    for (i, row) in rows.iter().enumerate() {
+        let (activations, beams) = process_line(&beams_in[i], row);
+        beams_in.push(beams);
+        timelines_in.push(process_line_part_2(&timelines_in[i], row));
+        splits_before.push(splits_before[i] + get_split_count(&activations));
+    }
+    let split_total = splits_before[rows.len()];
+    let timeline_total = timelines_in[rows.len()]
+        .iter()
+        .copied()
+        .fold(0, add_timelines);
+
+    // Timelines reaching the floor for each one leaving each column of each row
+    let mut weights_out: Vec<Vec<u128>> = vec![vec![]; rows.len()];
+    if let Some(last) = rows.last() {
+        weights_out[rows.len() - 1] = vec![1; last.len()];
+    }
+    for i in (0..rows.len().saturating_sub(1)).rev() {
+        let below = rows[i + 1];
+        weights_out[i] = (0..below.len())
+            .map(|col| get_outgoing_weight(below, col, &weights_out[i + 1]))
+            .collect();
+    }
+
+    let mut sensitivities = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        for (col, &cell) in row.iter().enumerate() {
+            if !ELEMENTS[get_element_index(cell)].is_splitter {
+                continue;
+            }
+            let mut emptied = row.to_vec();
+            emptied[col] = b'.';
+
+            let (activations, mut beams) = process_line(&beams_in[i], &emptied);
+            let mut split_count = splits_before[i] + get_split_count(&activations);
+            let mut last = i;
+            while last + 1 < rows.len() && beams != beams_in[last + 1] {
+                last += 1;
+                let (activations, next_beams) = process_line(&beams, rows[last]);
+                split_count += get_split_count(&activations);
+                beams = next_beams;
+            }
+            split_count += split_total - splits_before[last + 1];
+
+            let (mut gained, mut lost) = (0, 0);
+            for neighbour in col.saturating_sub(1)..(col + 2).min(row.len()) {
+                let count = timelines_in[i].get(neighbour).copied().unwrap_or(0);
+                let weight = |row: &[u8]| {
+                    multiply_timelines(count, get_outgoing_weight(row, neighbour, &weights_out[i]))
+                };
+                lost = add_timelines(lost, weight(row));
+                gained = add_timelines(gained, weight(&emptied));
+            }
+
+            sensitivities.push(Sensitivity {
+                row: i,
+                col,
+                symbol: cell,
+                split_count,
+                timeline_count: add_timelines(timeline_total, gained) - lost,
+            });
+        }
+    }
+    sensitivities
+}
+
+// A signed difference such as `+3` or `-12`, worked out without converting counts that may not fit in an
+// i128
+pub fn format_change(before: u128, after: u128) -> String {
+    let sign = if after >= before { '+' } else { '-' };
+    format!("{}{}", sign, after.abs_diff(before))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Re-runs both parts on the manifold with each splitter emptied in turn
+    fn analyse_splitters_slowly(input: &str) -> Vec<Sensitivity> {
+        let rows: Vec<&str> = input.lines().collect();
+        let mut sensitivities = vec![];
+        for (i, row) in rows.iter().enumerate() {
+            for (col, cell) in row.bytes().enumerate() {
+                if !ELEMENTS[get_element_index(cell)].is_splitter {
+                    continue;
+                }
+                let mut emptied: Vec<String> = rows.iter().map(|row| row.to_string()).collect();
+                emptied[i].replace_range(col..col + 1, ".");
+                let emptied = emptied.join("\n");
+                let (activations, _) = crate::simulate(&emptied);
+                sensitivities.push(Sensitivity {
+                    row: i,
+                    col,
+                    symbol: cell,
+                    split_count: get_split_count(&activations),
+                    timeline_count: crate::part_2(&emptied),
+                });
+            }
+        }
+        sensitivities
+    }
+
+    #[test]
+    fn analyse_splitters_works() {
+        let sensitivities = analyse_splitters(crate::tests::EXAMPLE);
+        assert_eq!(sensitivities.len(), 22);
+        // Without the first splitter the beam runs straight down to the splitter in row 6
+        assert_eq!(
+            sensitivities[0],
+            Sensitivity {
+                row: 2,
+                col: 7,
+                symbol: b'^',
+                split_count: 6,
+                timeline_count: 8
+            }
+        );
+        let find = |row, col| {
+            sensitivities
+                .iter()
+                .find(|sensitivity| (sensitivity.row, sensitivity.col) == (row, col))
+                .map(|sensitivity| (sensitivity.split_count, sensitivity.timeline_count))
+                .unwrap()
+        };
+        // No beam reaches the splitter at row 14, column 9, so removing it changes nothing, and removing
+        // the one above it at row 10 sends a beam down to it instead
+        assert_eq!(find(14, 9), (21, 40));
+        assert_eq!(find(10, 9), (21, 40));
+        assert_eq!(find(14, 13), (20, 39));
+        assert_eq!(
+            sensitivities,
+            analyse_splitters_slowly(crate::tests::EXAMPLE)
+        );
+    }
+
+    #[test]
+    fn format_change_works() {
+        assert_eq!(format_change(21, 21), "+0");
+        assert_eq!(format_change(40, 8), "-32");
+        assert_eq!(format_change(0, u128::MAX), format!("+{}", u128::MAX));
+        assert_eq!(format_change(u128::MAX, 1), format!("-{}", u128::MAX - 1));
+    }
+
+    #[test]
+    fn analyse_splitters_matches_re_running() {
+        let inputs = [
+            // Emptying either splitter lets the other one's sideways output through
+            "..S..\n..^^.\n.....",
+            ".S.S.\n.^^^.\n./.\\.\n.^#^.\n.....",
+            "..S...\n..<...\n.>.^..\n..^.^.\nS.....\n.^.^..\n......",
+            "..S..\n..^..\n.....\n....^",
+        ];
+        for input in inputs {
+            assert_eq!(analyse_splitters(input), analyse_splitters_slowly(input));
+        }
+    }
+}
@@ -40,6 +40,131 @@ fn part_1(coordinates: Vec<(u64, u64)>) -> (u64, (u64, u64), (u64, u64)) {
     )
 }
 
+// Sorted distinct values, each with its compressed index. A gap between two values that holds tiles gets an
+// index of its own between theirs, and index 0 and the index after the last value are padding outside the
+// loop
+fn compress(values: impl Iterator<Item = u64>) -> Vec<(u64, usize)> {
+    let mut values: Vec<u64> = values.collect();
+    values.sort();
+    values.dedup();
+    let mut compressed = vec![];
+    let mut index = 1;
+    for (i, &value) in values.iter().enumerate() {
+        if i > 0 && value - values[i - 1] > 1 {
+            index += 1;
+        }
+        compressed.push((value, index));
+        index += 1;
+    }
+    compressed
+}
+
+fn get_compressed_index(compressed: &[(u64, usize)], value: u64) -> usize {
+    let i = compressed
+        .binary_search_by_key(&value, |&(value, _)| value)
+        .expect("Value not compressed");
+    compressed[i].1
+}
+
+// Number of compressed indices, including the padding either side
+fn get_compressed_len(compressed: &[(u64, usize)]) -> usize {
+    compressed.last().map_or(0, |&(_, index)| index) + 2
+}
+
+// Marks the compressed cells on the loop and those enclosed by it
+fn get_inside_cells(
+    coordinates: &[(u64, u64)],
+    xs: &[(u64, usize)],
+    ys: &[(u64, usize)],
+) -> Vec<Vec<bool>> {
+    let width = get_compressed_len(xs);
+    let height = get_compressed_len(ys);
+    let mut on_loop = vec![vec![false; width]; height];
+    for (i, &(x1, y1)) in coordinates.iter().enumerate() {
+        let (x2, y2) = coordinates[(i + 1) % coordinates.len()];
+        if x1 != x2 && y1 != y2 {
+            panic!(
+                "Tiles {},{} and {},{} are not in the same row or column",
+                x1, y1, x2, y2
+            );
+        }
+        let (x1, x2) = (get_compressed_index(xs, x1), get_compressed_index(xs, x2));
+        let (y1, y2) = (get_compressed_index(ys, y1), get_compressed_index(ys, y2));
+        for row in on_loop.iter_mut().take(y1.max(y2) + 1).skip(y1.min(y2)) {
+            row[x1.min(x2)..=x1.max(x2)].fill(true);
+        }
+    }
+
+    // Anything the padding can reach without crossing the loop is outside
+    let mut outside = vec![vec![false; width]; height];
+    let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+    outside[0][0] = true;
+    while let Some((x, y)) = stack.pop() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx < width && ny < height && !on_loop[ny][nx] && !outside[ny][nx] {
+                outside[ny][nx] = true;
+                stack.push((nx, ny));
+            }
+        }
+    }
+    outside
+        .iter()
+        .map(|row| row.iter().map(|&cell| !cell).collect())
+        .collect()
+}
+
+// Number of inside cells above and to the left of each compressed cell, with an extra row and column of
+// zeros
+fn get_prefix_sums(inside: &[Vec<bool>]) -> Vec<Vec<usize>> {
+    let width = inside.first().map_or(0, |row| row.len());
+    let mut sums = vec![vec![0; width + 1]; inside.len() + 1];
+    for (y, row) in inside.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            sums[y + 1][x + 1] = sums[y][x + 1] + sums[y + 1][x] - sums[y][x] + cell as usize;
+        }
+    }
+    sums
+}
+
+// Like part 1, but the rectangle may only cover tiles on or inside the loop the red tiles make in order.
+// Every compressed cell the rectangle covers has to be inside, which the prefix sums check in constant time
+fn part_2(coordinates: &[(u64, u64)]) -> (u64, (u64, u64), (u64, u64)) {
+    let xs = compress(coordinates.iter().map(|coordinates| coordinates.0));
+    let ys = compress(coordinates.iter().map(|coordinates| coordinates.1));
+    let sums = get_prefix_sums(&get_inside_cells(coordinates, &xs, &ys));
+
+    let mut result = (0, (0u64, 0u64), (0u64, 0u64));
+    for (i, &(x1, y1)) in coordinates.iter().enumerate() {
+        for &(x2, y2) in &coordinates[i..] {
+            let rect_area = (x1.abs_diff(x2) + 1) * (y1.abs_diff(y2) + 1);
+            if rect_area <= result.0 {
+                continue;
+            }
+            let (left, right) = (
+                get_compressed_index(&xs, x1.min(x2)),
+                get_compressed_index(&xs, x1.max(x2)),
+            );
+            let (top, bottom) = (
+                get_compressed_index(&ys, y1.min(y2)),
+                get_compressed_index(&ys, y1.max(y2)),
+            );
+            let inside_count = sums[bottom + 1][right + 1] + sums[top][left]
+                - sums[top][right + 1]
+                - sums[bottom + 1][left];
+            if inside_count == (right - left + 1) * (bottom - top + 1) {
+                result = (rect_area, (x1, y1), (x2, y2));
+            }
+        }
+    }
+    result
+}
+
 fn main() {
     match fs::read_to_string("./day-9//assets/input.txt") {
         Ok(contents) => {
@@ -48,12 +173,19 @@ fn main() {
                 .map(|line| parse_coordinates(line))
                 .collect::<Vec<(u64, u64)>>();
 
-            let result = part_1(coordinates);
+            let result = part_1(coordinates.clone());
 
             println!(
                 "Largest area of {} is between coordinates {},{} and {},{}",
                 result.0, result.1 .0, result.1 .1, result.2 .0, result.2 .1
             );
+
+            let result = part_2(&coordinates);
+
+            println!(
+                "Largest area inside the loop of {} is between coordinates {},{} and {},{}",
+                result.0, result.1 .0, result.1 .1, result.2 .0, result.2 .1
+            );
         }
         Err(e) => eprintln!("Error reading file: {}", e),
     }
@@ -82,4 +214,58 @@ mod tests {
         ];
         assert_eq!(part_1(input.clone()), (50, (11,1), (2, 5)));
     }
+
+    #[test]
+    fn part_2_works() {
+        let input = vec![
+            (7, 1),
+            (11, 1),
+            (11, 7),
+            (9, 7),
+            (9, 5),
+            (2, 5),
+            (2, 3),
+            (7, 3),
+        ];
+        assert_eq!(part_2(&input), (24, (9, 5), (2, 3)));
+    }
+
+    #[test]
+    fn part_2_excludes_notches() {
+        // A U shape, where the widest rectangle between the arms would cover the notch at the top
+        let input = vec![
+            (0, 0),
+            (2, 0),
+            (2, 6),
+            (8, 6),
+            (8, 0),
+            (10, 0),
+            (10, 10),
+            (0, 10),
+        ];
+        assert_eq!(part_1(input.clone()).0, 121);
+        assert_eq!(part_2(&input), (45, (2, 6), (10, 10)));
+    }
+
+    #[test]
+    fn part_2_ignores_gaps_without_tiles() {
+        // The notch between x = 2 and x = 3 holds no tiles, so the loop covers the whole square
+        let input = vec![
+            (0, 0),
+            (2, 0),
+            (2, 3),
+            (3, 3),
+            (3, 0),
+            (5, 0),
+            (5, 5),
+            (0, 5),
+        ];
+        assert_eq!(part_2(&input), (36, (0, 0), (5, 5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tiles 0,0 and 2,2 are not in the same row or column")]
+    fn part_2_rejects_diagonal_edges() {
+        part_2(&[(0, 0), (2, 2), (0, 2)]);
+    }
 }